@@ -0,0 +1,801 @@
+//! Drives an `app::State` without a window, for automated testing on
+//! machines with no display.
+
+use platform_types::{command, Button, Command, State, StateParams, Typed, ARGB, BLACK, GREY, PALETTE};
+use render::FrameBuffer;
+use replay::{Event, FrameIndex, Replay, Replayer};
+use xs::Seed;
+
+pub struct Headless {
+    replayer: Replayer<app::State>,
+    frame: FrameIndex,
+    commands: Vec<Command>,
+}
+
+impl Headless {
+    /// `events` are fed to the state on the frames they are paired with, as
+    /// they would be by a `Replayer`.
+    pub fn new(params: StateParams, events: Vec<(FrameIndex, Event)>) -> Self {
+        let seed = params.0;
+        let state = app::State::new(params);
+
+        Self::from_state(state, seed, events)
+    }
+
+    /// Like `new`, but starting from an already set up state, such as one
+    /// with a level loaded. `seed` is only used as the seed of the replay
+    /// that drives the state.
+    pub fn from_state(state: app::State, seed: Seed, events: Vec<(FrameIndex, Event)>) -> Self {
+        Self {
+            replayer: Replayer::new(state, Replay { seed, start: Vec::new(), events }),
+            frame: 0,
+            commands: Vec::new(),
+        }
+    }
+
+    pub fn state(&self) -> &app::State {
+        self.replayer.state()
+    }
+
+    /// How many frames have been run so far.
+    pub fn frame(&self) -> FrameIndex {
+        self.frame
+    }
+
+    pub fn run_frames(&mut self, count: FrameIndex) {
+        for _ in 0..count {
+            let (commands, _) = self.replayer.frame();
+            self.commands.clear();
+            self.commands.extend_from_slice(commands);
+            self.frame += 1;
+        }
+    }
+
+    /// Runs frames until every scripted event has happened, then one more so
+    /// the effects of the last event are visible.
+    pub fn run_to_end(&mut self) {
+        while !self.replayer.is_finished() {
+            self.run_frames(1);
+        }
+        self.run_frames(1);
+    }
+
+    /// The commands from the most recent frame.
+    pub fn commands(&self) -> &[Command] {
+        &self.commands
+    }
+
+    /// Renders the most recent frame at `scale` times the `command`
+    /// resolution.
+    pub fn render(&self, scale: u16) -> Image {
+        render_commands(&self.commands, scale)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    /// Row by row, from the top left.
+    pub pixels: Vec<ARGB>,
+}
+
+/// Renders the commands at `scale` times the `command` resolution. A scale of
+/// 0 is treated as 1.
+pub fn render_commands(commands: &[Command], scale: u16) -> Image {
+    let scale = scale.max(1);
+    let mut frame_buffer = FrameBuffer::from_size((
+        command::WIDTH * scale,
+        command::HEIGHT * scale,
+    ));
+
+    render::render(&mut frame_buffer, commands);
+
+    Image {
+        width: frame_buffer.width.into(),
+        height: frame_buffer.height.into(),
+        pixels: frame_buffer.buffer,
+    }
+}
+
+/// The most pixels `spacetime_image` draws, which keeps the image to a few
+/// hundred megabytes.
+pub const MAX_SPACETIME_PIXELS: usize = 1 << 26;
+
+/// A spacetime diagram that would have had more than `MAX_SPACETIME_PIXELS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TooBig {
+    pub instants: usize,
+    pub scale: u16,
+}
+
+impl core::fmt::Display for TooBig {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "a spacetime diagram of {} instants at a scale of {} would have more than {MAX_SPACETIME_PIXELS} pixels",
+            self.instants,
+            self.scale,
+        )
+    }
+}
+
+impl std::error::Error for TooBig {}
+
+/// Draws a spacetime diagram of the whole timeline so far, from instant 0 up
+/// to the current instant or the last one with a splat, whichever is later.
+/// Each instant is `scale` pixels wide, and each cell `scale` pixels high.
+/// The current instant is marked with a grey line.
+pub fn spacetime_image(state: &game::State, axis: game::Axis, scale: u16) -> Result<Image, TooBig> {
+    let scale = scale.max(1);
+    let last = state.instants.last_used().unwrap_or(0).max(state.current);
+    let instants = last as usize + 1;
+
+    let too_big = TooBig { instants, scale };
+    let scale = usize::from(scale);
+    let width = instants.checked_mul(scale).ok_or(too_big)?;
+    let height = usize::from(axis.cell_count()) * scale;
+    match width.checked_mul(height) {
+        Some(count) if count <= MAX_SPACETIME_PIXELS => {},
+        _ => return Err(too_big),
+    }
+    let mut pixels = vec![BLACK; width * height];
+
+    let mut fill = |column: usize, row: usize, colour: ARGB| {
+        for y in row * scale..(row + 1) * scale {
+            for x in column * scale..(column + 1) * scale {
+                pixels[y * width + x] = colour;
+            }
+        }
+    };
+
+    for row in 0..usize::from(axis.cell_count()) {
+        fill(state.current as usize, row, GREY);
+    }
+
+    for (instant, splat) in state.spacetime_splats(0..=last) {
+        fill(
+            instant as usize,
+            usize::from(axis.coordinate(splat.x, splat.y)),
+            PALETTE[game::self_colour(splat.self_id) as usize],
+        );
+    }
+
+    // Both fit, since there are not that many pixels.
+    Ok(Image { width: width as u32, height: height as u32, pixels })
+}
+
+impl Image {
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        let mut encoder = png::Encoder::new(&mut output, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut data = Vec::with_capacity(self.pixels.len() * 3);
+        for &pixel in &self.pixels {
+            let [_, r, g, b] = pixel.to_be_bytes();
+            data.extend_from_slice(&[r, g, b]);
+        }
+
+        // Writing to a `Vec` cannot fail, and the size always matches the data.
+        encoder.write_header()
+            .and_then(|mut writer| writer.write_image_data(&data))
+            .expect("writing a PNG to memory should not fail");
+
+        output
+    }
+
+    pub fn from_png(bytes: &[u8]) -> Result<Self, png::DecodingError> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data)?;
+
+        let channels = info.color_type.samples();
+        let pixels = data[..info.buffer_size()]
+            .chunks_exact(channels)
+            .map(|channel| {
+                let (r, g, b) = match *channel {
+                    [grey] | [grey, _] => (grey, grey, grey),
+                    [r, g, b] | [r, g, b, _] => (r, g, b),
+                    _ => (0, 0, 0),
+                };
+                u32::from_be_bytes([0xFF, r, g, b])
+            })
+            .collect();
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    /// If the images differ, returns an image with the differing pixels in
+    /// bright red, over a darkened copy of `self`.
+    pub fn diff(&self, other: &Image) -> Option<Image> {
+        if self == other {
+            return None
+        }
+
+        let width = self.width.max(other.width);
+        let height = self.height.max(other.height);
+        let get = |image: &Image, x: u32, y: u32| {
+            if x < image.width && y < image.height {
+                image.pixels.get((y * image.width + x) as usize).copied()
+            } else {
+                None
+            }
+        };
+
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            for x in 0..width {
+                let ours = get(self, x, y);
+                pixels.push(if ours == get(other, x, y) {
+                    // Quarter brightness, so the differences stand out.
+                    (ours.unwrap_or_default() >> 2) & 0x003F3F3F | 0xFF000000
+                } else {
+                    0xFFFF0000
+                });
+            }
+        }
+
+        Some(Image { width, height, pixels })
+    }
+}
+
+/// Parses a script of button events, one per line, in this form:
+///
+/// ```text
+/// # Comments and blank lines are ignored.
+/// 0 press start
+/// 1 release start
+/// ```
+///
+/// The number is the frame the event happens before. The buttons are named
+/// `a`, `b`, `select`, `start`, `up`, `down`, `left` and `right`. Lines like
+/// `2 type 4000` type each character in turn, and `3 backspace` types a
+/// backspace.
+pub fn parse_script(script: &str) -> Result<Vec<(FrameIndex, Event)>, String> {
+    let mut output = Vec::new();
+
+    for (i, line) in script.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue
+        }
+
+        let mut words = line.split_whitespace();
+        let (Some(frame), Some(kind), argument, None) = (
+            words.next(),
+            words.next(),
+            words.next(),
+            words.next(),
+        ) else {
+            return Err(format!("line {line_number}: expected `FRAME press|release BUTTON`"))
+        };
+
+        let frame: FrameIndex = frame.parse()
+            .map_err(|_| format!("line {line_number}: invalid frame: {frame}"))?;
+        if output.last().is_some_and(|&(previous, _)| frame < previous) {
+            return Err(format!("line {line_number}: frames must not go backwards"));
+        }
+
+        let button = match (kind, argument) {
+            ("type", Some(text)) => {
+                output.extend(text.chars().map(|c| (frame, Event::Typed(Typed::Char(c)))));
+                continue
+            },
+            ("backspace", None) => {
+                output.push((frame, Event::Typed(Typed::Backspace)));
+                continue
+            },
+            (_, Some(button)) => button,
+            (_, None) => return Err(format!("line {line_number}: expected `FRAME press|release BUTTON`")),
+        };
+
+        let button = match button {
+            "a" => Button::A,
+            "b" => Button::B,
+            "select" => Button::SELECT,
+            "start" => Button::START,
+            "up" => Button::UP,
+            "down" => Button::DOWN,
+            "left" => Button::LEFT,
+            "right" => Button::RIGHT,
+            _ => return Err(format!("line {line_number}: unknown button: {button}")),
+        };
+
+        let event = match kind {
+            "press" => Event::Press(button),
+            "release" => Event::Release(button),
+            _ => return Err(format!("line {line_number}: expected press, release, type or backspace, not {kind}")),
+        };
+
+        output.push((frame, event));
+    }
+
+    Ok(output)
+}
+
+
+#[cfg(test)]
+mod running_a_script {
+    use super::*;
+
+    #[test]
+    fn that_presses_start_leaves_the_main_menu() {
+        let script = parse_script("
+            # Get past the main menu.
+            1 press start
+            2 release start
+        ").unwrap();
+        assert_eq!(script.len(), 2);
+
+        let mut headless = Headless::new(([1; 16], None, None), script);
+        assert_eq!(headless.state().game_state.time_mode, game::TimeMode::MainMenu);
+
+        headless.run_to_end();
+
+        assert_eq!(headless.state().game_state.time_mode, game::TimeMode::Flowing);
+        assert!(!headless.commands().is_empty());
+        assert!(headless.render(1).pixels.iter().any(|&pixel| pixel != platform_types::BLACK));
+    }
+
+    #[test]
+    fn that_types_a_time_sets_the_target() {
+        let script = parse_script("
+            1 press start
+            2 release start
+            3 press start
+            4 release start
+            5 type 4001
+            6 backspace
+            7 type 0
+        ").unwrap();
+
+        let mut headless = Headless::new(([1; 16], None, None), script);
+        headless.run_to_end();
+
+        let game::TimeMode::Manipulating(time_input) = headless.state().game_state.time_mode else {
+            panic!("expected to be manipulating time");
+        };
+        assert_eq!(time_input.get_value(), 4000);
+    }
+
+    #[test]
+    fn that_confirms_the_current_instant_goes_back_to_playing_as_the_same_self() {
+        let mut headless = Headless::new(([1; 16], None, None), parse_script("
+            1 press start
+            2 release start
+            3 press right
+            4 release right
+        ").unwrap());
+        headless.run_to_end();
+        let game_state = &headless.state().game_state;
+        let (self_id, last_self_id, history) =
+            (game_state.self_id, game_state.last_self_id, game_state.history.clone());
+        assert!(history.can_undo());
+
+        let mut headless = Headless::from_state(headless.replayer.into_inner(), [1; 16], parse_script("
+            1 press start
+            2 release start
+            3 press start
+            4 release start
+        ").unwrap());
+        headless.run_to_end();
+
+        let game_state = &headless.state().game_state;
+        assert_eq!(game_state.time_mode, game::TimeMode::Flowing);
+        assert_eq!((game_state.self_id, game_state.last_self_id), (self_id, last_self_id));
+        assert_eq!(game_state.history, history);
+    }
+
+    #[test]
+    fn that_picks_a_bookmark_sets_the_target_to_it() {
+        let script = parse_script("
+            1 press start
+            2 release start
+            3 press a
+            4 release a
+            5 type start
+            6 press start
+            7 release start
+            8 press right
+            9 release right
+            10 press right
+            11 release right
+            12 press start
+            13 release start
+            14 type b
+            15 press start
+            16 release start
+        ").unwrap();
+
+        let mut headless = Headless::new(([1; 16], None, None), script);
+        headless.run_to_end();
+
+        let game_state = &headless.state().game_state;
+        assert_eq!(game_state.bookmarks.len(), 1);
+        assert_eq!(game_state.bookmarks[0].name, "start");
+
+        let game::TimeMode::Manipulating(time_input) = game_state.time_mode else {
+            panic!("expected to be manipulating time");
+        };
+        assert_eq!(time_input.get_value(), game_state.bookmarks[0].instant);
+        assert_ne!(game_state.current, game_state.bookmarks[0].instant);
+    }
+
+    #[test]
+    fn that_holds_two_directions_moves_diagonally_and_repeats() {
+        let start = "
+            1 press start
+            2 release start
+        ";
+        let mut headless = Headless::new(([1; 16], None, None), parse_script(start).unwrap());
+        headless.run_to_end();
+        let before = headless.state().game_state.player;
+
+        // With the default delay of 12 frames and rate of 3, this moves on
+        // frames 3, 15, 18 and 21.
+        let script = parse_script(&format!("{start}
+            3 press right
+            3 press down
+            22 release right
+            22 release down
+        ")).unwrap();
+        let mut headless = Headless::new(([1; 16], None, None), script);
+        headless.run_to_end();
+        let after = headless.state().game_state.player;
+
+        assert_eq!(after.x.get().0 - before.x.get().0, 4);
+        assert_eq!(after.y.get().0 - before.y.get().0, 4);
+    }
+
+    #[test]
+    fn that_holds_a_button_while_manipulating_time_repeats_it() {
+        let script = parse_script("
+            1 press start
+            2 release start
+            3 press start
+            4 release start
+            # Adds 100 on frames 4, 16, 19 and 22.
+            4 press a
+            23 release a
+        ").unwrap();
+
+        let mut headless = Headless::new(([1; 16], None, None), script);
+        headless.run_to_end();
+
+        let game_state = &headless.state().game_state;
+        let game::TimeMode::Manipulating(time_input) = game_state.time_mode else {
+            panic!("expected to be manipulating time");
+        };
+        assert_eq!(time_input.get_value(), game_state.current + 400);
+    }
+
+    #[test]
+    fn that_leaves_a_won_level_starts_afresh_from_the_menu() {
+        let mut state = app::State::new(([1; 16], None, None));
+        state.game_state.load_level(&game::levels::all()[0]);
+        state.game_state.level = None;
+        state.game_state.step(None);
+        state.game_state.time_mode = game::TimeMode::Won;
+
+        let script = parse_script("
+            1 press start
+            2 release start
+        ").unwrap();
+        let mut headless = Headless::from_state(state, [1; 16], script);
+        headless.run_to_end();
+
+        let game_state = &headless.state().game_state;
+        assert_eq!(game_state.time_mode, game::TimeMode::MainMenu);
+        assert_eq!(game_state.instants.used_count(), 0);
+        assert_eq!(game_state.terrain, game::Terrain::default());
+    }
+
+    #[test]
+    fn with_frames_out_of_order_is_an_error() {
+        assert!(parse_script("2 press a\n1 release a").is_err());
+    }
+}
+
+#[cfg(test)]
+mod starting_from_the_start_of_a_replay {
+    use super::*;
+
+    #[test]
+    fn gives_the_same_game_quick_save_and_repeats() {
+        let mut state = app::State::new(([1; 16], None, None));
+        state.controls.auto_shift = app::AutoShift { delay: 20, rate: 5 };
+        state.game_state.load_level(&game::levels::all()[0]);
+        state.quick_save();
+        let saved = state.game_state.to_bytes();
+        state.game_state.step(Some(game::Dir::Right));
+
+        let mut started = app::State::from_start_bytes(([1; 16], None, None), &state.start_bytes())
+            .unwrap();
+
+        assert_eq!(started.save_path, None);
+        assert_eq!(started.controls.auto_shift, state.controls.auto_shift);
+        assert_eq!(started.game_state.to_bytes(), state.game_state.to_bytes());
+        started.quick_load();
+        assert_eq!(started.game_state.to_bytes(), saved);
+    }
+}
+
+#[cfg(test)]
+mod spacetime_image_shows {
+    use super::*;
+
+    #[test]
+    fn each_self_at_each_instant_in_their_colour() {
+        let mut state = game::State::new([1; 16]);
+        state.player.x = game::xy::x(10);
+        state.time_mode = game::TimeMode::Flowing;
+        for _ in 0..4 {
+            state.step(Some(game::Dir::Right));
+        }
+        state.jump_to(1);
+        let start_x = 11;
+
+        let image = spacetime_image(&state, game::Axis::X, 1).unwrap();
+
+        assert_eq!(image.width, 4);
+        assert_eq!(image.height, u32::from(game::xy::MAX_W_INNER) + 1);
+
+        let pixel = |instant: u32, x: u32| image.pixels[(x * image.width + instant) as usize];
+        for instant in 0..4 {
+            assert_eq!(pixel(instant, start_x + instant), PALETTE[game::self_colour(0) as usize]);
+        }
+        // The new self, who has not left a splat yet.
+        assert_eq!(pixel(1, start_x + 3), PALETTE[game::self_colour(1) as usize]);
+        assert_eq!(pixel(1, 0), GREY);
+    }
+
+    #[test]
+    fn an_error_instead_of_an_image_too_big_to_hold() {
+        let mut state = game::State::new([1; 16]);
+        state.current = game::InstantIndex::MAX;
+
+        assert_eq!(
+            spacetime_image(&state, game::Axis::X, u16::MAX),
+            Err(TooBig { instants: game::InstantIndex::MAX as usize + 1, scale: u16::MAX })
+        );
+    }
+}
+
+/// Compares frames against the PNGs in the `golden` folder. Run with the
+/// `UPDATE_GOLDEN` environment variable set to write the current frames out
+/// as the new goldens, after checking they look right. A frame that does not
+/// match is written to `golden-diffs` in the target directory, along with a
+/// picture of where it differs, and the test failure says where those are.
+/// The target directory is `CARGO_TARGET_DIR`, if that is set.
+#[cfg(test)]
+mod golden_frames {
+    use super::*;
+
+    const SEED: xs::Seed = [0x5E; 16];
+
+    fn assert_matches_golden(name: &str, script: &str) {
+        let events = parse_script(script).unwrap();
+        let mut headless = Headless::new((SEED, None, None), events);
+        headless.run_to_end();
+        let actual = headless.render(1);
+
+        let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+        let golden_path = manifest_dir.join("golden").join(format!("{name}.png"));
+
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::write(&golden_path, actual.to_png()).unwrap();
+            return
+        }
+
+        let golden = std::fs::read(&golden_path).unwrap_or_else(|e| panic!(
+            "could not read {}: {e}\nRun with UPDATE_GOLDEN=1 to create it.",
+            golden_path.display()
+        ));
+        let expected = Image::from_png(&golden).unwrap();
+
+        if let Some(diff) = expected.diff(&actual) {
+            // A relative `CARGO_TARGET_DIR` is taken to be from the
+            // workspace, where cargo is usually run.
+            let workspace_dir = manifest_dir.parent().unwrap_or(manifest_dir);
+            let target_dir = std::env::var_os("CARGO_TARGET_DIR")
+                .map_or_else(|| workspace_dir.join("target"), |dir| workspace_dir.join(dir));
+            let diff_dir = target_dir.join("golden-diffs");
+            std::fs::create_dir_all(&diff_dir).unwrap();
+            let actual_path = diff_dir.join(format!("{name}.actual.png"));
+            let diff_path = diff_dir.join(format!("{name}.diff.png"));
+            std::fs::write(&actual_path, actual.to_png()).unwrap();
+            std::fs::write(&diff_path, diff.to_png()).unwrap();
+
+            panic!(
+                "{name} does not match {}. See {} and {}",
+                golden_path.display(),
+                actual_path.display(),
+                diff_path.display(),
+            );
+        }
+    }
+
+    #[test]
+    fn on_the_main_menu() {
+        assert_matches_golden("main_menu", "");
+    }
+
+    #[test]
+    fn on_flowing_play() {
+        assert_matches_golden("flowing", "
+            1 press start
+            2 release start
+            5 press right
+            6 release right
+            8 press down
+            9 release down
+        ");
+    }
+
+    #[test]
+    fn on_the_spacetime_view() {
+        // Walk right, jump back, walk down, then open the view.
+        assert_matches_golden("spacetime", "
+            1 press start
+            2 release start
+            3 press right
+            4 release right
+            5 press right
+            6 release right
+            7 press right
+            8 release right
+            9 press right
+            10 release right
+            11 press right
+            12 release right
+            13 press right
+            14 release right
+            15 press right
+            16 release right
+            17 press right
+            18 release right
+            19 press right
+            20 release right
+            21 press right
+            22 release right
+            23 press start
+            24 release start
+            25 press down
+            26 release down
+            27 press start
+            28 release start
+            29 press down
+            30 release down
+            31 press down
+            32 release down
+            33 press down
+            34 release down
+            35 press down
+            36 release down
+            37 press down
+            38 release down
+            39 press b
+            40 release b
+        ");
+    }
+
+    #[test]
+    fn on_the_time_scrubber() {
+        // Walk, jump back and walk some more, so some instants have two
+        // selves, then stand still and pick a target that lands on a past
+        // self.
+        assert_matches_golden("scrubber", "
+            1 press start
+            2 release start
+            3 press right
+            4 release right
+            5 press right
+            6 release right
+            7 press right
+            8 release right
+            9 press right
+            10 release right
+            11 press right
+            12 release right
+            13 press right
+            14 release right
+            15 press right
+            16 release right
+            17 press start
+            18 release start
+            19 press down
+            20 release down
+            21 press start
+            22 release start
+            23 press up
+            24 release up
+            25 press up
+            26 release up
+            27 press up
+            28 release up
+            29 press up
+            30 release up
+            31 press up
+            32 release up
+            33 press up
+            34 release up
+            35 press up
+            36 release up
+            51 press start
+            52 release start
+            53 press left
+            54 release left
+        ");
+    }
+
+    #[test]
+    fn on_a_level_with_a_door_held_open() {
+        // Pick the second level, stand on the plate for a while, then jump
+        // back and head for the door while the past self holds it open.
+        assert_matches_golden("door", "
+            1 press down
+            2 release down
+            3 press down
+            4 release down
+            5 press start
+            6 release start
+            7 press up
+            8 release up
+            9 press up
+            10 release up
+            11 press up
+            12 release up
+            13 press up
+            14 release up
+            15 press right
+            16 release right
+            30 press start
+            31 release start
+            32 type 0
+            33 press start
+            34 release start
+            35 press right
+            36 release right
+            37 press right
+            38 release right
+            39 press right
+            40 release right
+            41 press right
+            42 release right
+            43 press right
+            44 release right
+            45 press right
+            46 release right
+            47 press down
+            48 release down
+            49 press down
+            50 release down
+        ");
+    }
+
+    #[test]
+    fn on_the_paradox_screen() {
+        // Standing still, then jumping back a bit, lands right on a past self.
+        assert_matches_golden("paradox", "
+            1 press start
+            2 release start
+            12 press start
+            13 release start
+            14 press left
+            15 release left
+            16 press start
+            17 release start
+        ");
+    }
+}
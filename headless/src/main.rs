@@ -0,0 +1,158 @@
+const USAGE: &str = "\
+usage: headless [OPTIONS]
+
+Runs time-travel-sim without a window, then writes out the last frame.
+
+options:
+    --seed HEX          start with this seed (defaults to all zeroes)
+    --script PATH       feed in the button events from this script
+    --replay PATH       feed in the events from this replay, starting the way
+                        it did, with its seed
+    --level PATH        start playing the level in this level file, instead of
+                        starting on the main menu
+    --frames N          run exactly N frames, instead of until the input runs out
+    --commands PATH     write the commands of the last frame to this file
+    --frame PATH        write the last frame to this file, as a PNG image
+    --spacetime PATH    write a spacetime diagram of the whole timeline to this
+                        file, as a PNG image
+    --axis x|y          the coordinate the spacetime diagram plots (defaults to x)
+    --scale N           render the frame at N times the base size of 480x320,
+                        and the spacetime diagram at N pixels per instant
+    --help              print this message
+
+Script lines look like `12 press start` or `20 release left`, where the number
+is the frame the event happens before. `30 type 4000` types each character in
+turn, and `31 backspace` types a backspace. Lines starting with # are ignored.";
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut seed = xs::Seed::default();
+    let mut seed_given = false;
+    let mut replay_given = false;
+    let mut start = Vec::new();
+    let mut events = Vec::new();
+    let mut frames = None;
+    let mut commands_path = None;
+    let mut frame_path = None;
+    let mut spacetime_path = None;
+    let mut axis = game::Axis::default();
+    let mut scale = 1;
+    let mut level_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+
+        match arg.as_str() {
+            "--seed" => {
+                let hex = value()?;
+                seed = xs::seed_from_hex(&hex)
+                    .ok_or_else(|| format!("invalid seed: {hex} (expected up to 32 hex digits)"))?;
+                seed_given = true;
+            },
+            "--script" => {
+                let path = value()?;
+                let script = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("could not read {path}: {e}"))?;
+                events = headless::parse_script(&script)
+                    .map_err(|e| format!("{path}: {e}"))?;
+            },
+            "--replay" => {
+                let path = value()?;
+                let replay = std::fs::read(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| replay::Replay::from_bytes(&bytes).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("could not load replay {path}: {e}"))?;
+                seed = replay.seed;
+                start = replay.start;
+                events = replay.events;
+                replay_given = true;
+            },
+            "--frames" => {
+                let count = value()?;
+                frames = Some(count.parse().map_err(|_| format!("invalid frame count: {count}"))?);
+            },
+            "--level" => level_path = Some(value()?),
+            "--commands" => commands_path = Some(value()?),
+            "--frame" => frame_path = Some(value()?),
+            "--spacetime" => spacetime_path = Some(value()?),
+            "--axis" => {
+                axis = match value()?.as_str() {
+                    "x" => game::Axis::X,
+                    "y" => game::Axis::Y,
+                    other => return Err(format!("invalid axis: {other} (expected x or y)")),
+                };
+            },
+            "--scale" => {
+                let n = value()?;
+                scale = match n.parse() {
+                    Ok(0) | Err(_) => return Err(format!("invalid scale: {n}")),
+                    Ok(n) => n,
+                };
+            },
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(())
+            },
+            _ => return Err(format!("unknown argument: {arg}\n{USAGE}")),
+        }
+    }
+
+    // Checked here rather than while parsing, so it does not matter which
+    // of them comes first.
+    if seed_given && replay_given {
+        return Err("--seed cannot be used with --replay, which has its own seed".to_string());
+    }
+    if level_path.is_some() && replay_given {
+        return Err("--level cannot be used with --replay, which starts the way the recording did".to_string());
+    }
+
+    fn logger(s: &str) {
+        println!("{}", s);
+    }
+
+    fn error_logger(s: &str) {
+        eprintln!("{}", s);
+    }
+
+    let mut state = app::State::from_start_bytes((seed, Some(logger), Some(error_logger)), &start)
+        .map_err(|e| format!("could not load the start of the replay: {e}"))?;
+    if let Some(path) = level_path {
+        state.load_level(&path).map_err(|e| format!("could not load level {path}: {e}"))?;
+    }
+
+    let mut headless = headless::Headless::from_state(state, seed, events);
+
+    match frames {
+        Some(count) => headless.run_frames(count),
+        None => headless.run_to_end(),
+    }
+
+    if let Some(path) = commands_path {
+        let mut text = String::new();
+        for command in headless.commands() {
+            text.push_str(&format!("{command:?}\n"));
+        }
+        std::fs::write(&path, text).map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    if let Some(path) = frame_path {
+        let png = headless.render(scale).to_png();
+        std::fs::write(&path, png).map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    if let Some(path) = spacetime_path {
+        let png = headless::spacetime_image(&headless.state().game_state, axis, scale)
+            .map_err(|e| format!("could not draw {path}: {e}; try a smaller --scale"))?
+            .to_png();
+        std::fs::write(&path, png).map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    Ok(())
+}
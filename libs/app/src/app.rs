@@ -1,14 +1,196 @@
-use game::{AdvanceOutcome, Splat};
+use game::{Action, AdvanceOutcome, Axis, BranchIndex, Cell, Dir, ParadoxKind, Rules, SpacetimeView, Splat};
 use gfx::{Commands};
 #[allow(unused_imports)]
-use platform_types::{command, sprite, unscaled, Button, Input, Speaker, SFX};
+use platform_types::{command, sprite, unscaled, Button, Speaker, Typed, SFX};
 pub use platform_types::StateParams;
 
+use std::path::{Path, PathBuf};
+
 pub struct State {
     pub game_state: Box<game::State>,
     pub commands: Commands,
-    pub input: Input,
+    pub controls: Controls,
+    /// What has been typed since the last frame.
+    pub typed: Vec<Typed>,
     pub speaker: Speaker,
+    /// Where `quick_save` and `quick_load` read and write. If this is `None`
+    /// then they use `quick_saved` instead.
+    pub save_path: Option<PathBuf>,
+    /// What `quick_save` last saved, when there is no `save_path`.
+    pub quick_saved: Option<Vec<u8>>,
+}
+
+/// How held buttons repeat, counted in frames of the game's own clock rather
+/// than left to the key repeat of the OS, so that it is the same everywhere,
+/// including in replays. A button acts as soon as it is pressed, then again
+/// once it has been held for `delay` frames, then every `rate` frames after
+/// that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AutoShift {
+    pub delay: u32,
+    pub rate: u32,
+}
+
+impl Default for AutoShift {
+    fn default() -> Self {
+        Self {
+            delay: 12,
+            rate: 3,
+        }
+    }
+}
+
+const DIRECTIONS: Button = Button::from_bits(
+    Button::UP.bits() | Button::DOWN.bits() | Button::LEFT.bits() | Button::RIGHT.bits()
+);
+
+/// Buttons that repeat on the same clock. The directions share one, so that
+/// holding two of them repeats a diagonal move, rather than each direction
+/// on its own.
+const REPEAT_GROUPS: [Button; 5] = [DIRECTIONS, Button::A, Button::B, Button::SELECT, Button::START];
+
+fn any_of(buttons: Button, group: Button) -> bool {
+    buttons.bits() & group.bits() != 0
+}
+
+/// Works out which buttons to act on each frame, from the presses and
+/// releases since the last one.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Controls {
+    pub auto_shift: AutoShift,
+    /// The buttons that are currently down.
+    held: Button,
+    /// The buttons that went down since the last frame, including ones that
+    /// have already come back up.
+    pressed: Button,
+    /// How many frames each of `REPEAT_GROUPS` has been held for, since a
+    /// button in it was last pressed.
+    held_frames: [u32; REPEAT_GROUPS.len()],
+}
+
+impl Controls {
+    /// A press of a button that is already down is a key repeat from the OS,
+    /// which is ignored, since `auto_shift` takes care of repeats.
+    pub fn press(&mut self, button: Button) {
+        if !self.held.contains(button) {
+            self.pressed.insert(button);
+        }
+        self.held.insert(button);
+    }
+
+    pub fn release(&mut self, button: Button) {
+        self.held.remove(button);
+    }
+
+    /// The buttons to act on this frame. Meant to be called once a frame.
+    fn tick(&mut self) -> Presses {
+        let pressed = core::mem::take(&mut self.pressed);
+        // A button that was tapped between frames still counts, even though
+        // it is no longer down.
+        let down = Button::from_bits(self.held.bits() | pressed.bits());
+        let AutoShift { delay, rate } = self.auto_shift;
+
+        let mut repeated = Button::default();
+        for (&group, held_frames) in REPEAT_GROUPS.iter().zip(&mut self.held_frames) {
+            let acting = if any_of(pressed, group) {
+                *held_frames = 0;
+                true
+            } else if any_of(down, group) {
+                *held_frames += 1;
+                *held_frames >= delay
+                && (*held_frames - delay).is_multiple_of(core::cmp::max(rate, 1))
+            } else {
+                *held_frames = 0;
+                false
+            };
+
+            // Every button in the group that is down acts at once, so
+            // holding two directions gives a diagonal move.
+            if acting {
+                repeated.insert(Button::from_bits(down.bits() & group.bits()));
+            }
+        }
+
+        // When opposite directions are both down, the one that was just
+        // pressed wins, or neither does.
+        for (a, b) in [(Button::UP, Button::DOWN), (Button::LEFT, Button::RIGHT)] {
+            if repeated.contains(a) && repeated.contains(b) {
+                if !pressed.contains(a) || pressed.contains(b) {
+                    repeated.remove(a);
+                }
+                if !pressed.contains(b) || pressed.contains(a) {
+                    repeated.remove(b);
+                }
+            }
+        }
+
+        Presses { pressed, repeated }
+    }
+}
+
+/// The buttons to act on for a single frame.
+#[derive(Clone, Copy, Debug, Default)]
+struct Presses {
+    pressed: Button,
+    /// Includes repeats of held buttons, as set out by an `AutoShift`.
+    repeated: Button,
+}
+
+impl Presses {
+    fn pressed_this_frame(&self, button: Button) -> bool {
+        self.pressed.contains(button)
+    }
+
+    /// For things that are handy to keep doing by holding a button down,
+    /// like moving or scrolling, rather than toggles, which would flicker.
+    fn pressed_or_repeated(&self, button: Button) -> bool {
+        self.repeated.contains(button)
+    }
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Io(std::io::Error),
+    Save(game::save::Error),
+    Terrain(game::TerrainError),
+    Level(game::level_file::Error),
+}
+
+impl core::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::Save(e) => write!(f, "{e}"),
+            Self::Terrain(e) => write!(f, "{e}"),
+            Self::Level(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<std::io::Error> for LoadError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<game::save::Error> for LoadError {
+    fn from(e: game::save::Error) -> Self {
+        Self::Save(e)
+    }
+}
+
+impl From<game::TerrainError> for LoadError {
+    fn from(e: game::TerrainError) -> Self {
+        Self::Terrain(e)
+    }
+}
+
+impl From<game::level_file::Error> for LoadError {
+    fn from(e: game::level_file::Error) -> Self {
+        Self::Level(e)
+    }
 }
 
 impl State {
@@ -19,18 +201,112 @@ impl State {
         }
 
         // We always want to log the seed, if there is a logger available, so use the function,
-        // not the macro.
-        features::log(&format!("{:?}", seed));
+        // not the macro. It is logged as hex so it can be passed back in with `--seed`.
+        features::log(&format!(
+            "seed: {}",
+            seed.iter().map(|byte| format!("{byte:02x}")).collect::<String>()
+        ));
 
         let game_state = game::State::new(seed);
 
         Self {
             game_state,
             commands: Commands::default(),
-            input: Input::default(),
+            controls: Controls::default(),
+            typed: Vec::new(),
             speaker: Speaker::default(),
+            save_path: None,
+            quick_saved: None,
         }
     }
+
+    /// Everything, on top of the seed, needed to set up a state that starts
+    /// out the same way as this one, for `replay::Replay::start`. That is
+    /// how held buttons repeat, the game, and whatever `quick_load` would
+    /// load, which means reading the save file, if there is one.
+    pub fn start_bytes(&self) -> Vec<u8> {
+        let AutoShift { delay, rate } = self.controls.auto_shift;
+        let game = self.game_state.to_bytes();
+        let quick_saved = match &self.save_path {
+            Some(path) => std::fs::read(path).ok(),
+            None => self.quick_saved.clone(),
+        };
+
+        let mut bytes = Vec::with_capacity(12 + game.len());
+        bytes.extend_from_slice(&delay.to_le_bytes());
+        bytes.extend_from_slice(&rate.to_le_bytes());
+        bytes.extend_from_slice(&(game.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&game);
+        // A save is never empty, so an empty rest means there is none.
+        bytes.extend(quick_saved.unwrap_or_default());
+        bytes
+    }
+
+    /// Sets up a state from the bytes `start_bytes` gave, or from the seed
+    /// alone if there are none. The state has no `save_path`, so playing
+    /// back quick saves and loads does not touch the save file.
+    pub fn from_start_bytes(params: StateParams, bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut output = Self::new(params);
+        if bytes.is_empty() {
+            return Ok(output)
+        }
+
+        let (delay, rest) = bytes.split_first_chunk::<4>()
+            .ok_or(game::save::Error::UnexpectedEnd)?;
+        let (rate, rest) = rest.split_first_chunk::<4>()
+            .ok_or(game::save::Error::UnexpectedEnd)?;
+        let (len, rest) = rest.split_first_chunk::<4>()
+            .ok_or(game::save::Error::UnexpectedEnd)?;
+        let len = u32::from_le_bytes(*len) as usize;
+        if len > rest.len() {
+            return Err(game::save::Error::UnexpectedEnd.into())
+        }
+        let (game, quick_saved) = rest.split_at(len);
+
+        output.controls.auto_shift = AutoShift {
+            delay: u32::from_le_bytes(*delay),
+            rate: u32::from_le_bytes(*rate),
+        };
+        output.game_state = game::State::from_bytes(game)?;
+        output.quick_saved = (!quick_saved.is_empty()).then(|| quick_saved.to_vec());
+
+        Ok(output)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        std::fs::write(path, self.game_state.to_bytes())
+    }
+
+    /// If this returns an error, then the current game state is left as is.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let bytes = std::fs::read(path)?;
+
+        self.game_state = game::State::from_bytes(&bytes)?;
+
+        Ok(())
+    }
+
+    /// Loads walls from a picture of the grid, in the form that
+    /// `game::Terrain::parse` takes. If this returns an error, then the
+    /// current game state is left as is.
+    pub fn load_terrain(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let text = std::fs::read_to_string(path)?;
+
+        self.game_state.set_terrain(game::Terrain::parse(&text)?);
+
+        Ok(())
+    }
+
+    /// Loads a level file, in the form that `game::level_file::parse` takes,
+    /// and starts playing it. If this returns an error, then the current game
+    /// state is left as is.
+    pub fn load_level(&mut self, path: impl AsRef<Path>) -> Result<(), LoadError> {
+        let text = std::fs::read_to_string(path)?;
+
+        self.game_state.load_level(&game::level_file::parse(&text)?);
+
+        Ok(())
+    }
 }
 
 impl platform_types::State for State {
@@ -40,81 +316,293 @@ impl platform_types::State for State {
         update_and_render(
             &mut self.commands,
             &mut self.game_state,
-            self.input,
+            self.controls.tick(),
+            &self.typed,
             &mut self.speaker,
         );
 
-        self.input.previous_gamepad = self.input.gamepad;
+        self.typed.clear();
 
         (self.commands.slice(), self.speaker.slice())
     }
 
     fn press(&mut self, button: Button) {
-        if self.input.previous_gamepad.contains(button) {
-            //This is meant to pass along the key repeat, if any.
-            //Not sure if rewriting history is the best way to do this.
-            self.input.previous_gamepad.remove(button);
-        }
-
-        self.input.gamepad.insert(button);
+        self.controls.press(button);
     }
 
     fn release(&mut self, button: Button) {
-        self.input.gamepad.remove(button);
+        self.controls.release(button);
+    }
+
+    fn typed(&mut self, typed: Typed) {
+        self.typed.push(typed);
+    }
+
+    fn quick_save(&mut self) {
+        if let Some(path) = &self.save_path {
+            match self.save(path) {
+                Ok(()) => features::log(&format!("saved to {}", path.display())),
+                Err(e) => features::log(&format!("could not save to {}: {e}", path.display())),
+            }
+        } else {
+            self.quick_saved = Some(self.game_state.to_bytes());
+        }
+    }
+
+    fn quick_load(&mut self) {
+        if let Some(path) = self.save_path.clone() {
+            match self.load(&path) {
+                Ok(()) => features::log(&format!("loaded {}", path.display())),
+                Err(e) => features::log(&format!("could not load {}: {e}", path.display())),
+            }
+        } else if let Some(bytes) = &self.quick_saved {
+            match game::State::from_bytes(bytes) {
+                Ok(game_state) => self.game_state = game_state,
+                Err(e) => features::log(&format!("could not load the quick save: {e}")),
+            }
+        }
     }
 }
 
-fn update(state: &mut game::State, input: Input, speaker: &mut Speaker) {
+fn update(state: &mut game::State, input: Presses, typed: &[Typed], speaker: &mut Speaker) {
     use game::TimeMode::*;
     match &mut state.time_mode {
         MainMenu => {
             if input.pressed_this_frame(Button::START) {
-                state.time_mode = Flowing;
+                match state.level {
+                    Some(index) => state.start_level(index),
+                    None => state.time_mode = Flowing,
+                }
+            } else if input.pressed_or_repeated(Button::UP) {
+                state.level = match state.level {
+                    None => None,
+                    Some(0) => None,
+                    Some(index) => Some(index - 1),
+                };
+            } else if input.pressed_or_repeated(Button::DOWN) {
+                let count = game::levels::all().len() as game::LevelIndex;
+                state.level = match state.level {
+                    None if count > 0 => Some(0),
+                    Some(index) if index + 1 < count => Some(index + 1),
+                    level => level,
+                };
+            } else if input.pressed_or_repeated(Button::LEFT) {
+                state.rules = match state.rules {
+                    Rules::Paradox => Rules::SelfConsistent,
+                    Rules::Branching => Rules::Paradox,
+                    Rules::SelfConsistent => Rules::Branching,
+                };
+            } else if input.pressed_or_repeated(Button::RIGHT) {
+                state.rules = match state.rules {
+                    Rules::Paradox => Rules::Branching,
+                    Rules::Branching => Rules::SelfConsistent,
+                    Rules::SelfConsistent => Rules::Paradox,
+                };
             }
         },
         Flowing => {
-            if input.pressed_this_frame(Button::START) {
+            let mut undo = false;
+            let mut redo = false;
+            for &typed in typed {
+                match typed {
+                    Typed::Char('u') => undo = true,
+                    Typed::Char('r') => redo = true,
+                    Typed::Char('t') => state.ghost_view.hide_trails = !state.ghost_view.hide_trails,
+                    _ => {},
+                }
+            }
+
+            if undo {
+                state.undo();
+            } else if redo {
+                state.redo();
+            } else if input.pressed_this_frame(Button::START) {
                 state.time_mode = Manipulating(state.fresh_time_input());
+            } else if state.rules == Rules::Branching
+            && input.pressed_this_frame(Button::SELECT) {
+                state.time_mode = ChoosingBranch(state.branches.active());
+            } else if input.pressed_this_frame(Button::A) {
+                let index = state.add_bookmark();
+                state.time_mode = NamingBookmark(index);
+            } else if input.pressed_this_frame(Button::B) {
+                state.time_mode = Spacetime(SpacetimeView {
+                    axis: Axis::default(),
+                    centre: state.current,
+                });
             } else {
-                if input.pressed_this_frame(Button::UP) {
-                    state.move_up();
-                } else if input.pressed_this_frame(Button::DOWN) {
-                    state.move_down();
-                } else if input.pressed_this_frame(Button::LEFT) {
-                    state.move_left();
-                } else if input.pressed_this_frame(Button::RIGHT) {
-                    state.move_right();
-                }
+                let vertical = if input.pressed_or_repeated(Button::UP) {
+                    Some(Dir::Up)
+                } else if input.pressed_or_repeated(Button::DOWN) {
+                    Some(Dir::Down)
+                } else {
+                    None
+                };
+                let horizontal = if input.pressed_or_repeated(Button::LEFT) {
+                    Some(Dir::Left)
+                } else if input.pressed_or_repeated(Button::RIGHT) {
+                    Some(Dir::Right)
+                } else {
+                    None
+                };
+                let dir = match (vertical, horizontal) {
+                    (Some(vertical), Some(horizontal)) => Dir::diagonal(vertical, horizontal),
+                    (vertical, horizontal) => vertical.or(horizontal),
+                };
 
-                state.advance_time();
+                state.step_undoably(dir);
             }
         },
         Manipulating(ref mut time_input) => {
-            if input.pressed_this_frame(Button::START) {
-                state.current = time_input.get_value();
-                state.check_collision();
-            } else if input.pressed_this_frame(Button::UP) {
+            let mut list_bookmarks = false;
+            for &typed in typed {
+                match typed {
+                    Typed::Char('b') => list_bookmarks = true,
+                    Typed::Char(c) => if let Some(digit) = c.to_digit(10) {
+                        time_input.type_digit(digit as u8);
+                    },
+                    Typed::Backspace => time_input.backspace(),
+                }
+            }
+
+            if list_bookmarks && !state.bookmarks.is_empty() {
+                state.time_mode = ChoosingBookmark(*time_input, 0);
+            } else if input.pressed_this_frame(Button::START) {
+                let target = time_input.get_value();
+                // Jumping to the instant the player is already at would
+                // only start a new self for nothing.
+                if target == state.current {
+                    state.time_mode = Flowing;
+                } else {
+                    state.jump_to_undoably(target);
+                }
+            } else if input.pressed_or_repeated(Button::UP) {
                 time_input.saturating_add(10);
-            } else if input.pressed_this_frame(Button::DOWN) {
+            } else if input.pressed_or_repeated(Button::DOWN) {
                 time_input.saturating_sub(10);
-            } else if input.pressed_this_frame(Button::LEFT) {
+            } else if input.pressed_or_repeated(Button::LEFT) {
                 time_input.saturating_sub(1);
-            } else if input.pressed_this_frame(Button::RIGHT) {
+            } else if input.pressed_or_repeated(Button::RIGHT) {
                 time_input.saturating_add(1);
-            } else if input.pressed_this_frame(Button::A) {
+            } else if input.pressed_or_repeated(Button::A) {
                 time_input.saturating_add(100);
-            } else if input.pressed_this_frame(Button::B) {
+            } else if input.pressed_or_repeated(Button::B) {
                 time_input.saturating_sub(100);
             } else if input.pressed_this_frame(Button::SELECT) {
                 time_input.reset();
             }
         }
         Collision(_) => {
-            // TODO? allow backing out of the paradox?
+            if input.pressed_this_frame(Button::SELECT) {
+                state.rewind();
+            }
+        }
+        ChoosingBranch(ref mut highlighted) => {
+            if input.pressed_this_frame(Button::START) {
+                let index = *highlighted;
+                state.switch_branch(index);
+                state.time_mode = Flowing;
+            } else if input.pressed_or_repeated(Button::UP) {
+                *highlighted = highlighted.saturating_sub(1);
+            } else if input.pressed_or_repeated(Button::DOWN) {
+                if *highlighted + 1 < state.branches.count() {
+                    *highlighted += 1;
+                }
+            } else if input.pressed_this_frame(Button::SELECT) {
+                state.time_mode = Flowing;
+            }
+        }
+        NamingBookmark(index) => {
+            if let Some(bookmark) = state.bookmarks.get_mut(*index as usize) {
+                for &typed in typed {
+                    match typed {
+                        Typed::Char(c) => if (c.is_ascii_graphic() || c == ' ')
+                        && bookmark.name.len() < game::BOOKMARK_NAME_MAX_LEN {
+                            bookmark.name.push(c);
+                        },
+                        Typed::Backspace => { bookmark.name.pop(); },
+                    }
+                }
+            }
+
+            if input.pressed_this_frame(Button::START) {
+                state.time_mode = Flowing;
+            }
+        }
+        ChoosingBookmark(ref mut time_input, ref mut highlighted) => {
+            if input.pressed_this_frame(Button::START) {
+                if let Some(bookmark) = state.bookmarks.get(*highlighted as usize) {
+                    time_input.set_value(bookmark.instant);
+                }
+                state.time_mode = Manipulating(*time_input);
+            } else if input.pressed_or_repeated(Button::UP) {
+                *highlighted = highlighted.saturating_sub(1);
+            } else if input.pressed_or_repeated(Button::DOWN) {
+                if (*highlighted as usize) + 1 < state.bookmarks.len() {
+                    *highlighted += 1;
+                }
+            } else if input.pressed_this_frame(Button::B) {
+                let (time_input, index) = (*time_input, *highlighted);
+                state.remove_bookmark(index);
+
+                state.time_mode = match state.bookmarks.len() {
+                    0 => Manipulating(time_input),
+                    len => ChoosingBookmark(
+                        time_input,
+                        index.min(len as game::BookmarkIndex - 1),
+                    ),
+                };
+            } else if input.pressed_this_frame(Button::SELECT) {
+                state.time_mode = Manipulating(*time_input);
+            }
+        }
+        Won => {
+            if input.pressed_this_frame(Button::START) {
+                // Levels loaded from elsewhere are not part of the list, so
+                // there is no next one to go to. The won game is thrown away,
+                // so that starting from the menu does not pick it back up.
+                match next_level(state) {
+                    Some(next) => state.start_level(next),
+                    None => state.return_to_menu(),
+                }
+            }
+        }
+        Spacetime(ref mut view) => {
+            if input.pressed_this_frame(Button::B) {
+                state.time_mode = Flowing;
+            } else if input.pressed_this_frame(Button::SELECT) {
+                view.axis = match view.axis {
+                    Axis::X => Axis::Y,
+                    Axis::Y => Axis::X,
+                };
+            } else if input.pressed_this_frame(Button::A) {
+                view.centre = state.current;
+            } else if input.pressed_or_repeated(Button::LEFT) {
+                view.centre = view.centre.saturating_sub(1);
+            } else if input.pressed_or_repeated(Button::RIGHT) {
+                view.centre = view.centre.saturating_add(1).min(state.budget.last_instant);
+            } else if input.pressed_or_repeated(Button::DOWN) {
+                view.centre = view.centre.saturating_sub(10);
+            } else if input.pressed_or_repeated(Button::UP) {
+                view.centre = view.centre.saturating_add(10).min(state.budget.last_instant);
+            }
         }
     }
 }
 
+fn next_level(state: &game::State) -> Option<game::LevelIndex> {
+    let next = state.level? + 1;
+    ((next as usize) < game::levels::all().len()).then_some(next)
+}
+
+/// Like "2 of 3: hold the door".
+fn level_title(index: game::LevelIndex) -> String {
+    let levels = game::levels::all();
+    match levels.get(index as usize) {
+        Some(level) => format!("{} of {}: {}", index + 1, levels.len(), level.name),
+        None => format!("{}", index + 1),
+    }
+}
+
 #[inline]
 fn render(commands: &mut Commands, state: &game::State) {
     use game::TimeMode::*;
@@ -140,8 +628,51 @@ fn render(commands: &mut Commands, state: &game::State) {
         () => {
             commands.draw_box(box_rect, 0);
 
-            let (splats, player_splat) = state.current_splats();
-            for &Splat { x, y, colour, .. } in splats {
+            // Walls are the same colour as the edge of the grid, since they
+            // stop the player in the same way.
+            let shown_instant = state.shown_instant();
+            for (x, y, cell) in state.terrain.iter() {
+                let colour = match cell {
+                    Cell::Floor => continue,
+                    Cell::Wall => 0,
+                    Cell::Plate(_) => 3,
+                    Cell::Door(channel) => if state.is_door_open(channel, shown_instant) {
+                        5
+                    } else {
+                        4
+                    },
+                    Cell::Goal => 1,
+                };
+
+                commands.draw_pixel(
+                    x.get() + X_OFFSET,
+                    y.get() + Y_OFFSET,
+                    colour
+                );
+            }
+
+            let ghosts = state.ghosts(
+                state.ghost_view.instant,
+                if state.ghost_view.hide_trails { 0 } else { game::GHOST_TRAIL_LEN },
+            );
+            // Trails go under every ghost, not just their own. The palette
+            // has nothing dimmer than grey that shows up on the background,
+            // so the older end of a trail fades out by leaving gaps instead.
+            for ghost in &ghosts {
+                for (i, &(x, y)) in ghost.trail.iter().enumerate() {
+                    let back = i + 1;
+                    if back > 2 && back % 2 == 1 {
+                        continue
+                    }
+
+                    commands.draw_pixel(
+                        x.get() + X_OFFSET,
+                        y.get() + Y_OFFSET,
+                        5
+                    );
+                }
+            }
+            for &game::Ghost { x, y, colour, .. } in &ghosts {
                 commands.draw_pixel(
                     x.get() + X_OFFSET,
                     y.get() + Y_OFFSET,
@@ -149,6 +680,7 @@ fn render(commands: &mut Commands, state: &game::State) {
                 );
             }
             {
+                let (_, player_splat) = state.current_splats();
                 let Splat { x, y, colour, .. } = player_splat;
 
                 commands.draw_pixel(
@@ -163,7 +695,10 @@ fn render(commands: &mut Commands, state: &game::State) {
                 AdvanceOutcome::Success => {}
                 AdvanceOutcome::OutOfInstants => {
                     commands.print(
-                        b"64k instants ought to be enough for anybody!",
+                        format!(
+                            "{} instants ought to be enough for anybody!",
+                            u64::from(state.budget.last_instant) + 1
+                        ).as_bytes(),
                         unscaled::X(0) + gfx::CHAR_W,
                         error_y,
                         6,
@@ -171,7 +706,10 @@ fn render(commands: &mut Commands, state: &game::State) {
                 },
                 AdvanceOutcome::OutOfSplats => {
                     commands.print(
-                        b"256 selves ought to be enough for anybody!",
+                        format!(
+                            "{} selves ought to be enough for anybody!",
+                            state.budget.splats_per_instant
+                        ).as_bytes(),
                         unscaled::X(0) + gfx::CHAR_W,
                         error_y,
                         6,
@@ -179,12 +717,65 @@ fn render(commands: &mut Commands, state: &game::State) {
                 },
             }
 
+            let time_text = match state.rules {
+                Rules::Paradox | Rules::SelfConsistent => format!("{}", state.current),
+                Rules::Branching => format!(
+                    "{} (branch {})",
+                    state.current,
+                    state.branches.active(),
+                ),
+            };
+
             commands.print(
-                format!("{}", state.current).as_bytes(),
+                time_text.as_bytes(),
                 box_rect.x,
                 time_y,
                 6,
             );
+
+            let self_text = format!("self {}", state.self_id);
+            commands.print(
+                self_text.as_bytes(),
+                box_rect.x + box_rect.w - gfx::CHAR_W * self_text.len() as unscaled::Inner,
+                time_y,
+                game::self_colour(state.self_id),
+            );
+
+            // A legend to the right of the grid, so each ghost can be told
+            // apart by colour as a particular past self.
+            if !ghosts.is_empty() {
+                let legend_x = box_rect.x + box_rect.w + gfx::CHAR_W;
+                let mut y = box_rect.y;
+                commands.print(b"past selves", legend_x, y, 6);
+                y += text_y_advance;
+
+                let rows = ((box_rect.h - (y - box_rect.y)).get() / text_y_advance.get()) as usize;
+                let shown = if ghosts.len() > rows {
+                    rows.saturating_sub(1)
+                } else {
+                    ghosts.len()
+                };
+                let mut ghosts = ghosts.iter().collect::<Vec<_>>();
+                ghosts.sort_by_key(|ghost| ghost.self_id);
+
+                for ghost in &ghosts[..shown] {
+                    commands.print(
+                        format!("self {}", ghost.self_id).as_bytes(),
+                        legend_x,
+                        y,
+                        ghost.colour,
+                    );
+                    y += text_y_advance;
+                }
+                if shown < ghosts.len() {
+                    commands.print(
+                        format!("+{} more", ghosts.len() - shown).as_bytes(),
+                        legend_x,
+                        y,
+                        6,
+                    );
+                }
+            }
         }
     }
 
@@ -194,58 +785,478 @@ fn render(commands: &mut Commands, state: &game::State) {
 
             macro_rules! p {
                 ($s: expr) => {
+                    p!($s, 6)
+                };
+                ($s: expr, $colour: expr) => {
                     commands.print(
                         format!("{}", $s).as_bytes(),
                         unscaled::X(0),
                         y,
-                        6,
+                        $colour,
                     );
                     y += text_y_advance;
                 }
             }
 
+            // Headings are in a different colour, rather than having blank
+            // lines between sections, so everything fits on screen.
+            macro_rules! heading {
+                ($s: expr) => {
+                    p!($s, 3)
+                }
+            }
+
             //  This line is just wide enough to fit on the screen
             //  ============================================================
             //  123456789112345678921234567893123456789412345678951234567896
             //           10        20        30        40        50        60
 
-            p!("");
-            p!("main mode controls");
+            heading!("main mode controls");
             p!("up/down/left/right           -                   move around");
             p!("enter                        -  enter time manipulation mode");
-            p!("");
-            p!("time manipulation mode controls");
+            p!("x/t                          -  spacetime view/ghost trails");
+            p!("u/r                          -  undo/redo a move or a jump");
+            heading!("time manipulation mode controls");
             p!("left/right                   -  subtract/add 1 time unit");
             p!("down/up                      -  subtract/add 10 time units");
             p!("x/z                          -  subtract/add 100 time units");
             p!("right shift                  -  reset to current time");
+            p!("0-9/backspace                -  type in a time");
             p!("enter                        -  submit and back to main mode");
-            p!("");
+            heading!("collision paradox controls");
+            p!("right shift                  -  undo what caused the paradox");
+            heading!("branching rules controls");
+            p!("right shift                  -  open/close the branch list");
+            p!("down/up                      -  next/previous branch");
+            p!("enter                        -  switch to highlighted branch");
+            heading!("spacetime view controls");
+            p!("left/right                   -  scroll by 1 instant");
+            p!("down/up                      -  scroll by 10 instants");
+            p!("z                            -  scroll back to current time");
+            p!("right shift                  -  switch between x and y");
+            p!("x                            -  back to main mode");
+            heading!("bookmark controls");
+            p!("z (main mode)                -  bookmark the current time");
+            p!("b (time manipulation mode)   -  list bookmarks");
+            p!("down/up/enter (in the list)  -  pick a bookmark");
+            p!("x (in the list)              -  delete highlighted bookmark");
+            p!(format!(
+                "rules (left/right to change): {}",
+                match state.rules {
+                    Rules::Paradox => "paradox",
+                    Rules::Branching => "branching",
+                    Rules::SelfConsistent => "self-consistent",
+                }
+            ));
+            p!(format!(
+                "level (down/up to change): {}",
+                match state.level {
+                    None => "free play".to_string(),
+                    Some(index) => level_title(index),
+                }
+            ));
             p!("                    press enter to start                    ");
 
         },
         Flowing => {
             render_game!();
+
+            if let Some(index) = state.level {
+                commands.print(
+                    level_title(index).as_bytes(),
+                    box_rect.x,
+                    box_rect.y + box_rect.h + text_y_advance,
+                    6,
+                );
+            }
+        },
+        Won => {
+            render_game!();
+
+            commands.print(
+                if next_level(state).is_some() {
+                    b"level complete! press enter for the next one"
+                } else {
+                    b"every level complete! press enter for the menu"
+                },
+                unscaled::X(0) + gfx::CHAR_W,
+                box_rect.y + box_rect.h + text_y_advance,
+                1,
+            );
         },
         Manipulating(ref time_input) => {
             render_game!();
 
+            let target = time_input.get_value();
+
             commands.print(
-                format!("{}", time_input.get_value()).as_bytes(),
+                format!(
+                    "{}{}",
+                    target,
+                    // A cursor, to show that digits will be added on.
+                    if time_input.is_typing() { "_" } else { "" },
+                ).as_bytes(),
                 box_rect.x,
                 manipulated_time_y,
                 2,
             );
+
+            // A bar below the grid, covering every instant used so far, with
+            // taller columns where more selves are around. It is as wide as
+            // the grid, so that it fits wherever the grid does, like in a
+            // terminal.
+            const BAR_W: unscaled::W = unscaled::W(game::xy::MAX_W_INNER as unscaled::Inner);
+            const BAR_H: unscaled::H = unscaled::H(16);
+            let bar_x = box_rect.x + unscaled::W(1);
+            let bar_y = box_rect.y + box_rect.h + text_y_advance;
+
+            let last = state.instants.last_used().unwrap_or(0)
+                .max(state.current)
+                .max(target);
+            let instant_count = u64::from(last) + 1;
+            let columns = u64::from(BAR_W.get());
+
+            let column_of = |instant: game::InstantIndex| {
+                unscaled::W((u64::from(instant) * columns / instant_count) as unscaled::Inner)
+            };
+
+            let densities: Vec<usize> = (0..columns)
+                .map(|column| {
+                    let first = column * instant_count / columns;
+                    let end = ((column + 1) * instant_count / columns).max(first + 1);
+
+                    state.instants
+                        .range(first as game::InstantIndex..=(end - 1) as game::InstantIndex)
+                        .map(|(_, splats)| splats.len())
+                        .max()
+                        .unwrap_or(0)
+                })
+                .collect();
+            let max_density = densities.iter().copied().max().unwrap_or(0).max(1);
+
+            commands.draw_box(
+                unscaled::Rect {
+                    x: bar_x - unscaled::W(1),
+                    y: bar_y - unscaled::H(1),
+                    w: BAR_W + unscaled::W(1),
+                    h: BAR_H + unscaled::H(1),
+                },
+                0,
+            );
+
+            for (column, &density) in densities.iter().enumerate() {
+                let height = (usize::from(BAR_H.get()) * density).div_ceil(max_density);
+                for offset in 1..=height as unscaled::Inner {
+                    commands.draw_pixel(
+                        bar_x + unscaled::W(column as unscaled::Inner),
+                        bar_y + BAR_H - unscaled::H(offset),
+                        5,
+                    );
+                }
+            }
+
+            // Tick off the bookmarks above the bar, under the other marks.
+            for bookmark in &state.bookmarks {
+                for offset in 2..=4 {
+                    commands.draw_pixel(
+                        bar_x + column_of(bookmark.instant),
+                        bar_y - unscaled::H(offset),
+                        1,
+                    );
+                }
+            }
+
+            // Mark the current instant in the player's colour and the target
+            // in the same colour as its number, sticking out past the bar.
+            for (instant, colour) in [
+                (state.current, game::self_colour(state.self_id)),
+                (target, 2),
+            ] {
+                let x = bar_x + column_of(instant);
+                let mut y = bar_y - unscaled::H(3);
+                while y < bar_y + BAR_H + unscaled::H(3) {
+                    commands.draw_pixel(x, y, colour);
+                    y += unscaled::H(1);
+                }
+            }
+
+            let labels_y = bar_y + BAR_H + unscaled::H(5);
+            commands.print(b"0", bar_x, labels_y, 6);
+            let last_text = format!("{last}");
+            commands.print(
+                last_text.as_bytes(),
+                bar_x + BAR_W - gfx::CHAR_W * last_text.len() as unscaled::Inner,
+                labels_y,
+                6,
+            );
+
+            if let Some(e) = state.pending_collision() {
+                let what = match (e.kind, state.instants
+                    .splats(e.instant)
+                    .get(e.splat_index as usize)) {
+                    (ParadoxKind::ShutIn, _) => "shut you in a door".to_string(),
+                    (_, Some(splat)) => format!("hit past self {}", splat.self_id),
+                    (_, None) => "hit a past self".to_string(),
+                };
+
+                commands.print(
+                    format!("jumping here would {what}").as_bytes(),
+                    unscaled::X(0) + gfx::CHAR_W,
+                    labels_y + text_y_advance,
+                    2,
+                );
+            }
+
+            match state.bookmarks.len() {
+                0 => {},
+                count => commands.print(
+                    format!(
+                        "{count} bookmark{}, type b to list them",
+                        if count == 1 { "" } else { "s" },
+                    ).as_bytes(),
+                    unscaled::X(0) + gfx::CHAR_W,
+                    labels_y + text_y_advance * 2,
+                    1,
+                ),
+            }
+        },
+        NamingBookmark(index) => {
+            render_game!();
+
+            if let Some(bookmark) = state.bookmarks.get(index as usize) {
+                commands.print(
+                    format!("bookmark at {}: {}_", bookmark.instant, bookmark.name).as_bytes(),
+                    box_rect.x,
+                    manipulated_time_y,
+                    3,
+                );
+            }
+
+            commands.print(
+                b"type a name if you like, then press enter",
+                unscaled::X(0) + gfx::CHAR_W,
+                box_rect.y + box_rect.h + text_y_advance,
+                5,
+            );
+        },
+        ChoosingBookmark(_, highlighted) => {
+            let mut y = unscaled::Y(0) + text_y_advance;
+
+            commands.print(
+                b"bookmarks: enter picks, x deletes, right shift goes back",
+                unscaled::X(0),
+                y,
+                6,
+            );
+            y += text_y_advance;
+
+            // Scroll so the highlighted bookmark is always on screen.
+            let rows = (unscaled::Y(command::HEIGHT) - y).get() / text_y_advance.get();
+            let first = (highlighted as usize).saturating_sub(usize::from(rows).saturating_sub(1));
+
+            for (index, bookmark) in state.bookmarks.iter().enumerate().skip(first) {
+                commands.print(
+                    format!("{:>8}  {}", bookmark.instant, bookmark.name).as_bytes(),
+                    unscaled::X(0) + gfx::CHAR_W,
+                    y,
+                    if index == highlighted as usize { 3 } else { 6 },
+                );
+                y += text_y_advance;
+            }
         },
-        Collision(_e) => {
+        Collision(e) => {
             render_game!();
 
+            // Ring the cell where the collision happened.
+            commands.draw_box(
+                unscaled::Rect {
+                    x: e.x.get() + X_OFFSET - unscaled::W(1),
+                    y: e.y.get() + Y_OFFSET - unscaled::H(1),
+                    w: unscaled::W(2),
+                    h: unscaled::H(2),
+                },
+                2,
+            );
+
+            let (title, verb) = match e.kind {
+                ParadoxKind::SameCell => ("collision paradox detected!", "hit"),
+                ParadoxKind::Crossed => ("consistency paradox detected!", "passed through"),
+                ParadoxKind::ShutIn => ("door paradox detected!", "got shut in"),
+                ParadoxKind::Blocked => ("consistency paradox detected!", "got in the way of"),
+            };
+
             commands.print(
-                b"collision paradox detected!",
+                title.as_bytes(),
                 unscaled::X(0),
-                paradox_error_y,
+                paradox_error_y - text_y_advance - text_y_advance,
                 2,
             );
+
+            let past_self = match (e.kind, state.instants
+                .splats(e.instant)
+                .get(e.splat_index as usize)) {
+                (ParadoxKind::ShutIn, _) => "a door".to_string(),
+                (_, Some(splat)) => format!("past self {}", splat.self_id),
+                (_, None) => "a past self".to_string(),
+            };
+
+            commands.print(
+                format!(
+                    "{verb} {past_self} at ({}, {}) at instant {}",
+                    e.x.get().0,
+                    e.y.get().0,
+                    e.instant,
+                ).as_bytes(),
+                unscaled::X(0),
+                paradox_error_y - text_y_advance,
+                2,
+            );
+
+            if let Some(rewind) = state.rewind {
+                let undone = match rewind.action {
+                    Action::Wait => "waiting".to_string(),
+                    Action::Move(dir) => format!("moving {}", match dir {
+                        Dir::Up => "up",
+                        Dir::Down => "down",
+                        Dir::Left => "left",
+                        Dir::Right => "right",
+                        Dir::UpLeft => "up and left",
+                        Dir::UpRight => "up and right",
+                        Dir::DownLeft => "down and left",
+                        Dir::DownRight => "down and right",
+                    }),
+                    Action::Jump { from, to } => format!("the jump from {from} to {to}"),
+                };
+
+                commands.print(
+                    format!("right shift to undo {undone}").as_bytes(),
+                    unscaled::X(0),
+                    paradox_error_y,
+                    2,
+                );
+            }
+        },
+        ChoosingBranch(highlighted) => {
+            let mut y = unscaled::Y(0) + text_y_advance;
+
+            commands.print(
+                b"branches",
+                unscaled::X(0),
+                y,
+                6,
+            );
+            y += text_y_advance;
+
+            // Scroll so the highlighted branch is always on screen.
+            let rows = (unscaled::Y(command::HEIGHT) - y).get() / text_y_advance.get();
+            let first = highlighted.saturating_sub(BranchIndex::from(rows).saturating_sub(1));
+
+            for index in first..state.branches.count() {
+                let Some(branch) = state.branches.get(index) else { continue };
+
+                let current = if index == state.branches.active() {
+                    state.current
+                } else {
+                    branch.current()
+                };
+
+                let text = match branch.parent {
+                    None => format!("branch {index}, now at {current}"),
+                    Some(parent) => format!(
+                        "branch {index}, from {parent} at {}, now at {current}",
+                        branch.forked_at,
+                    ),
+                };
+
+                let marker = if index == state.branches.active() {
+                    "*"
+                } else {
+                    " "
+                };
+
+                commands.print(
+                    format!("{marker} {text}").as_bytes(),
+                    unscaled::X(0) + gfx::CHAR_W,
+                    y,
+                    if index == highlighted { 3 } else { 6 },
+                );
+                y += text_y_advance;
+            }
+        },
+        Spacetime(SpacetimeView { axis, centre }) => {
+            // One instant per pixel across, and one pixel per cell down, the
+            // same as the grid itself. The plot is as wide as the grid too,
+            // so that it fits wherever the grid does, like in a terminal.
+            const PLOT_W: unscaled::W = unscaled::W(game::xy::MAX_W_INNER as unscaled::Inner);
+            const CELL_H: unscaled::H = unscaled::H(1);
+
+            let plot_h = CELL_H * axis.cell_count();
+            let plot_x = unscaled::X(0) + (unscaled::W(command::WIDTH) - PLOT_W) / 2;
+            let plot_y = unscaled::Y(0) + text_y_advance * 3;
+
+            let width = game::InstantIndex::from(PLOT_W.get());
+            let first = centre.saturating_sub(width / 2);
+            let last = first.saturating_add(width - 1);
+
+            commands.print(
+                format!(
+                    "spacetime view: {} against instant",
+                    match axis {
+                        Axis::X => "x",
+                        Axis::Y => "y",
+                    }
+                ).as_bytes(),
+                unscaled::X(0),
+                unscaled::Y(0),
+                6,
+            );
+            commands.print(
+                format!("instants {first} to {last}, now at {}", state.current).as_bytes(),
+                unscaled::X(0),
+                unscaled::Y(0) + text_y_advance,
+                6,
+            );
+
+            commands.draw_box(
+                unscaled::Rect {
+                    x: plot_x - unscaled::W(1),
+                    y: plot_y - unscaled::H(1),
+                    w: PLOT_W + unscaled::W(1),
+                    h: plot_h + unscaled::H(1),
+                },
+                0,
+            );
+
+            let column = |instant: game::InstantIndex| {
+                plot_x + unscaled::W((instant - first) as unscaled::Inner)
+            };
+
+            // Dot a line down the current instant, so it is easy to find.
+            if (first..=last).contains(&state.current) {
+                let mut y = plot_y;
+                while y < plot_y + plot_h {
+                    commands.draw_pixel(column(state.current), y, 5);
+                    y += CELL_H * 2;
+                }
+            }
+
+            for (instant, splat) in state.spacetime_splats(first..=last) {
+                let y = plot_y + CELL_H * axis.coordinate(splat.x, splat.y);
+                for offset in 0..CELL_H.get() {
+                    commands.draw_pixel(
+                        column(instant),
+                        y + unscaled::H(offset),
+                        game::self_colour(splat.self_id),
+                    );
+                }
+            }
+
+            commands.print(
+                b"arrows scroll, z goes to now, right shift switches axis",
+                unscaled::X(0),
+                plot_y + plot_h + text_y_advance,
+                5,
+            );
         },
     }
 }
@@ -254,9 +1265,11 @@ fn render(commands: &mut Commands, state: &game::State) {
 fn update_and_render(
     commands: &mut Commands,
     state: &mut game::State,
-    input: Input,
+    input: Presses,
+    typed: &[Typed],
     speaker: &mut Speaker,
 ) {
-    update(state, input, speaker);
+    update(state, input, typed, speaker);
+    state.ease_ghosts();
     render(commands, state);
 }
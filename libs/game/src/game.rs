@@ -7,7 +7,7 @@ pub mod xy {
 
     pub type Inner = u8;
 
-    #[derive(Clone, Copy, Default)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
     pub struct X(Inner);
 
     /// Clamps to the valid range
@@ -40,7 +40,7 @@ pub mod xy {
         }
     }
 
-    #[derive(Clone, Copy, Default)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
     pub struct Y(Inner);
 
     pub const MAX_H_INNER: Inner = 0x40;
@@ -75,41 +75,277 @@ pub mod xy {
 }
 pub use xy::{X, Y};
 
-#[derive(Clone, Copy, Default)]
+/// Links plates to the doors they open.
+pub type Channel = u8;
+
+/// What is on a cell of the grid, apart from any selves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Floor,
+    /// Nobody, whether the player or a past self, can stand here.
+    Wall,
+    /// Opens the doors on the same channel, at every instant someone is
+    /// standing on it.
+    Plate(Channel),
+    /// Like a wall, except at instants where someone is standing on a plate
+    /// on the same channel.
+    Door(Channel),
+    /// Where the player needs to get to, to finish a level.
+    Goal,
+}
+
+impl Cell {
+    /// Whether this cell is in the way at every instant.
+    pub fn blocks(self) -> bool {
+        match self {
+            Cell::Floor | Cell::Plate(_) | Cell::Door(_) | Cell::Goal => false,
+            Cell::Wall => true,
+        }
+    }
+}
+
+pub const TERRAIN_W: usize = xy::MAX_W_INNER as usize + 1;
+pub const TERRAIN_H: usize = xy::MAX_H_INNER as usize + 1;
+pub const TERRAIN_CELL_COUNT: usize = TERRAIN_W * TERRAIN_H;
+
+/// The cells that make up the grid. This does not change as time passes, so
+/// it is kept separately from the selves at each instant.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Terrain {
+    /// Row by row, from the top left. Empty until a cell other than
+    /// `Cell::Floor` is set, so an all floor grid costs nothing.
+    cells: Vec<Cell>,
+}
+
+impl Terrain {
+    fn index(x: X, y: Y) -> usize {
+        usize::from(y.get().get()) * TERRAIN_W + usize::from(x.get().get())
+    }
+
+    pub fn get(&self, x: X, y: Y) -> Cell {
+        self.cells.get(Self::index(x, y)).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, x: X, y: Y, cell: Cell) {
+        if self.cells.is_empty() {
+            if cell == Cell::Floor {
+                return
+            }
+            self.cells = vec![Cell::Floor; TERRAIN_CELL_COUNT];
+        }
+
+        self.cells[Self::index(x, y)] = cell;
+    }
+
+    /// Every cell, row by row, from the top left.
+    pub fn iter(&self) -> impl Iterator<Item = (X, Y, Cell)> + '_ {
+        self.cells.iter().enumerate().map(|(i, &cell)| (
+            xy::x((i % TERRAIN_W) as xy::Inner),
+            xy::y((i / TERRAIN_W) as xy::Inner),
+            cell,
+        ))
+    }
+
+    /// Parses a picture of the grid, with one line per row, where `#` is a
+    /// wall and `.` or a space is floor. Rows and lines that are left out
+    /// are floor.
+    pub fn parse(text: &str) -> Result<Terrain, TerrainError> {
+        Self::parse_at(text, 0, 0)
+    }
+
+    /// Like `parse`, but with the top left of the picture at the given
+    /// cell, rather than the top left of the grid.
+    pub fn parse_at(text: &str, left: xy::Inner, top: xy::Inner) -> Result<Terrain, TerrainError> {
+        let mut output = Terrain::default();
+
+        for (row, line) in text.lines().enumerate() {
+            let line_number = row + 1;
+            let y = row + usize::from(top);
+            if y >= TERRAIN_H {
+                if line.trim().is_empty() {
+                    continue
+                }
+                return Err(TerrainError::TooManyRows { line: line_number });
+            }
+
+            for (column, character) in line.chars().enumerate() {
+                let column_number = column + 1;
+                let cell = match character {
+                    '.' | ' ' => Cell::Floor,
+                    '#' => Cell::Wall,
+                    _ => return Err(TerrainError::UnknownCell {
+                        line: line_number,
+                        column: column_number,
+                        character,
+                    }),
+                };
+
+                let x = column + usize::from(left);
+                if x >= TERRAIN_W {
+                    if cell == Cell::Floor {
+                        continue
+                    }
+                    return Err(TerrainError::RowTooLong { line: line_number, column: column_number });
+                }
+
+                output.set(xy::x(x as xy::Inner), xy::y(y as xy::Inner), cell);
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TerrainError {
+    TooManyRows { line: usize },
+    /// `column` is the first one past the right edge of the grid.
+    RowTooLong { line: usize, column: usize },
+    UnknownCell { line: usize, column: usize, character: char },
+}
+
+impl core::fmt::Display for TerrainError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use TerrainError::*;
+        match self {
+            TooManyRows { line } => write!(
+                f,
+                "line {line}: the grid is only {TERRAIN_H} rows high"
+            ),
+            RowTooLong { line, column } => write!(
+                f,
+                "line {line}, column {column}: the grid is only {TERRAIN_W} cells wide"
+            ),
+            UnknownCell { line, column, character } => write!(
+                f,
+                "line {line}, column {column}: {character:?} is not a cell; expected `#`, `.` or a space"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TerrainError {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Player {
     pub x: X,
     pub y: Y,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Splat {
     pub x: X,
     pub y: Y,
     pub colour: PaletteIndex,
+    /// Which loop of the player this splat was left by.
+    pub self_id: SelfId,
+}
+
+pub type SplatIndex = u32;
+/// Identifies one loop of the player, from one time jump to the next. Each
+/// self appears at most once per instant.
+pub type SelfId = u32;
+
+/// The colours selves are drawn with, in order of `SelfId`, repeating once
+/// they run out. Red is left out, since that marks paradoxes.
+pub const SELF_COLOURS: [PaletteIndex; 6] = [6, 3, 4, 1, 0, 5];
+
+pub fn self_colour(self_id: SelfId) -> PaletteIndex {
+    SELF_COLOURS[self_id as usize % SELF_COLOURS.len()]
 }
+pub type InstantIndex = u32;
 
-pub const SPLAT_COUNT: u16 = u8::MAX as u16 + 1;
-pub type SplatIndex = u8;
+/// The default number of splats an instant can hold.
+pub const SPLAT_COUNT: SplatIndex = u8::MAX as SplatIndex;
+/// The default number of instants time can flow through.
+pub const INSTANT_COUNT: InstantIndex = u16::MAX as InstantIndex + 1;
 
-#[derive(Clone)]
-pub struct Instant {
-    pub splats: [Splat; SPLAT_COUNT as _],
-    pub one_past_last: SplatIndex,
+/// How much of the timeline a `State` is allowed to use. Storage is only
+/// allocated for what is actually written, so these can be set well past the
+/// defaults if needed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budget {
+    /// Time will not flow past this instant.
+    pub last_instant: InstantIndex,
+    /// The maximum amount of splats a single instant can hold.
+    pub splats_per_instant: SplatIndex,
 }
 
-impl Default for Instant {
+impl Default for Budget {
     fn default() -> Self {
         Self {
-            splats: [Splat::default(); SPLAT_COUNT as _],
-            one_past_last: 0,
+            last_instant: INSTANT_COUNT - 1,
+            splats_per_instant: SPLAT_COUNT,
         }
     }
 }
 
-pub const INSTANT_COUNT: u32 = u16::MAX as u32 + 1;
-pub type InstantIndex = u16;
+/// A sparse map from instants to the splats written at those instants.
+/// Instants that have never been written to do not take up any space.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Timeline {
+    instants: std::collections::BTreeMap<InstantIndex, Vec<Splat>>,
+}
+
+impl Timeline {
+    pub fn splats(&self, index: InstantIndex) -> &[Splat] {
+        self.instants.get(&index).map_or(&[], |splats| splats)
+    }
+
+    pub fn push(&mut self, index: InstantIndex, splat: Splat) {
+        self.instants.entry(index).or_default().push(splat);
+    }
+
+    /// Removes the most recently pushed splat at the given index, if any.
+    pub fn pop(&mut self, index: InstantIndex) -> Option<Splat> {
+        let splats = self.instants.get_mut(&index)?;
+        let output = splats.pop();
+        if splats.is_empty() {
+            self.instants.remove(&index);
+        }
+        output
+    }
+
+    /// The splat left at the given index by the given self, if any, along
+    /// with its index within the instant.
+    pub fn self_splat(
+        &self,
+        index: InstantIndex,
+        self_id: SelfId,
+    ) -> Option<(SplatIndex, &Splat)> {
+        self.splats(index)
+            .iter()
+            .enumerate()
+            .find(|(_, splat)| splat.self_id == self_id)
+            .map(|(i, splat)| (i as SplatIndex, splat))
+    }
+
+    /// Iterates over the instants that have at least one splat, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (InstantIndex, &[Splat])> {
+        self.instants.iter().map(|(&index, splats)| (index, &splats[..]))
+    }
+
+    /// The number of instants that have at least one splat.
+    pub fn used_count(&self) -> usize {
+        self.instants.len()
+    }
+
+    /// Like `iter`, but only over the instants in the given range.
+    pub fn range(
+        &self,
+        range: core::ops::RangeInclusive<InstantIndex>,
+    ) -> impl Iterator<Item = (InstantIndex, &[Splat])> {
+        self.instants.range(range).map(|(&index, splats)| (index, &splats[..]))
+    }
+
+    /// The last instant with at least one splat, if any.
+    pub fn last_used(&self) -> Option<InstantIndex> {
+        self.instants.last_key_value().map(|(&index, _)| index)
+    }
+}
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum AdvanceOutcome {
     #[default]
     Success,
@@ -117,197 +353,3254 @@ pub enum AdvanceOutcome {
     OutOfSplats,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct TimeInput {
     current: InstantIndex,
     initial: InstantIndex,
+    max: InstantIndex,
+    /// Whether `current` is being typed in digit by digit.
+    typing: bool,
 }
 
 impl TimeInput {
     pub fn saturating_add(&mut self, to_add: InstantIndex) {
-        self.current = self.current.saturating_add(to_add);
+        self.typing = false;
+        self.current = core::cmp::min(
+            self.current.saturating_add(to_add),
+            self.max,
+        );
     }
 
     pub fn saturating_sub(&mut self, to_sub: InstantIndex) {
+        self.typing = false;
         self.current = self.current.saturating_sub(to_sub);
     }
 
     pub fn reset(&mut self) {
+        self.typing = false;
         self.current = self.initial;
     }
 
+    /// Adds a digit to the end of the value, or starts a new value with it
+    /// if the value was not being typed in already. Values past the maximum
+    /// are clamped to it.
+    pub fn type_digit(&mut self, digit: u8) {
+        if !self.typing {
+            self.typing = true;
+            self.current = 0;
+        }
+
+        self.current = core::cmp::min(
+            self.current
+                .saturating_mul(10)
+                .saturating_add(InstantIndex::from(digit.min(9))),
+            self.max,
+        );
+    }
+
+    /// Removes the last digit of the value.
+    pub fn backspace(&mut self) {
+        self.typing = true;
+        self.current /= 10;
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing
+    }
+
     pub fn get_value(&self) -> InstantIndex {
         self.current
     }
+
+    /// Sets the value outright, clamped to the maximum.
+    pub fn set_value(&mut self, value: InstantIndex) {
+        self.typing = false;
+        self.current = core::cmp::min(value, self.max);
+    }
+}
+
+/// How the player got in the way of a past self.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ParadoxKind {
+    /// The player ended up in the same cell as a past self.
+    #[default]
+    SameCell,
+    /// The player and a past self crossed paths, either by swapping cells or
+    /// by moving diagonally across each other. Only a paradox under
+    /// `Rules::SelfConsistent`.
+    Crossed,
+    /// The player was in a doorway when nobody was holding the door open.
+    /// This is a paradox under every set of rules.
+    ShutIn,
+    /// The player stood in one of the corners a past self squeezed between
+    /// when moving diagonally, so it could not have made its recorded move.
+    /// Only a paradox under `Rules::SelfConsistent`.
+    Blocked,
+}
+
+/// Whether two moves of at most one cell each way, made over the same
+/// instant, cross paths. Only moves of the same kind can share a midpoint,
+/// and those that do either swap cells, cross diagonally, or are the same.
+fn crosses(a_from: (X, Y), a_to: (X, Y), b_from: (X, Y), b_to: (X, Y)) -> bool {
+    let sum = |(x0, y0): (X, Y), (x1, y1): (X, Y)| {
+        (x0.get().0 + x1.get().0, y0.get().0 + y1.get().0)
+    };
+
+    a_from != b_from && sum(a_from, a_to) == sum(b_from, b_to)
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[must_use]
 pub struct CollisionError {
-    // TODO? The location where the collision happened?
+    /// The instant the collision happened at.
+    pub instant: InstantIndex,
+    pub x: X,
+    pub y: Y,
+    /// The index, within the instant, of the splat of the past self that was
+    /// collided with. Lower indexes are from earlier loops. Always 0 for
+    /// `ParadoxKind::ShutIn`, where no past self is involved.
+    pub splat_index: SplatIndex,
+    pub kind: ParadoxKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Dir {
+    /// The direction that goes both of the given ways at once, if there is
+    /// one, that is, if one is vertical and the other horizontal.
+    pub fn diagonal(vertical: Dir, horizontal: Dir) -> Option<Dir> {
+        use Dir::*;
+        match (vertical, horizontal) {
+            (Up, Left) => Some(UpLeft),
+            (Up, Right) => Some(UpRight),
+            (Down, Left) => Some(DownLeft),
+            (Down, Right) => Some(DownRight),
+            _ => None,
+        }
+    }
+}
+
+/// Something the player did that can cause a paradox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Let time flow without moving.
+    Wait,
+    Move(Dir),
+    Jump {
+        from: InstantIndex,
+        to: InstantIndex,
+    },
+}
+
+/// Enough of a `State` to return to how it was just before the action that
+/// caused a paradox. Time does not advance into a paradox, so apart from
+/// undoing a `Wait`, the timeline itself does not need to change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rewind {
+    pub action: Action,
+    player: Player,
+    current: InstantIndex,
+    time_mode: TimeMode,
+    self_id: SelfId,
+}
+
+/// Enough to take back a move or a jump, along with any waiting after it,
+/// without keeping a copy of the whole `State` around. Every splat left since
+/// was left by the same self, at the instants from `splats_from` on, so they
+/// can be popped off again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Done {
+    pub action: Action,
+    player: Player,
+    current: InstantIndex,
+    self_id: SelfId,
+    last_self_id: SelfId,
+    splats_from: InstantIndex,
+    /// Whether the action forked a new branch, which undoing it throws away.
+    forked: bool,
+}
+
+/// A move or a jump that was undone, so that it can be redone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Undone {
+    pub action: Action,
+    /// The instant it was undone back to, which is where it starts from.
+    from: InstantIndex,
+    /// The instant time had flowed to when it was undone.
+    until: InstantIndex,
+}
+
+/// The moves and jumps that can be undone, and the ones that can be redone.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct History {
+    done: Vec<Done>,
+    undone: Vec<Undone>,
+}
+
+impl History {
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+}
+
+/// The rules for what happens when the player travels back in time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rules {
+    /// There is a single timeline, and meeting a past self is a paradox.
+    #[default]
+    Paradox,
+    /// Jumping back in time forks a new branch of the timeline, leaving the
+    /// original history intact. Meeting a past self is allowed.
+    Branching,
+    /// There is a single timeline, and every past self must be able to
+    /// follow its recorded path. Blocking a past self, or passing through
+    /// one, is a paradox.
+    SelfConsistent,
+}
+
+pub type BranchIndex = u32;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Branch {
+    /// The branch this one was forked from. `None` for the original branch.
+    pub parent: Option<BranchIndex>,
+    /// The instant that was jumped back to, to create this branch.
+    pub forked_at: InstantIndex,
+    // These are only kept up to date while the branch is not the active one.
+    // The active branch's versions are the fields of the same name in `State`.
+    instants: Timeline,
+    current: InstantIndex,
+    player: Player,
+    self_id: SelfId,
+}
+
+impl Branch {
+    /// The instant the player was at when they left this branch.
+    pub fn current(&self) -> InstantIndex {
+        self.current
+    }
+}
+
+/// All the branches of the timeline. There is always at least the original
+/// branch, and exactly one branch is active.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Branches {
+    list: Vec<Branch>,
+    active: BranchIndex,
+}
+
+impl Default for Branches {
+    fn default() -> Self {
+        Self {
+            list: vec![Branch::default()],
+            active: 0,
+        }
+    }
+}
+
+impl Branches {
+    pub fn active(&self) -> BranchIndex {
+        self.active
+    }
+
+    pub fn count(&self) -> BranchIndex {
+        self.list.len() as BranchIndex
+    }
+
+    pub fn get(&self, index: BranchIndex) -> Option<&Branch> {
+        self.list.get(index as usize)
+    }
+}
+
+pub type BookmarkIndex = u32;
+
+/// Bookmark names longer than this are cut short.
+pub const BOOKMARK_NAME_MAX_LEN: usize = 16;
+
+/// A marked instant, to make it easy to get back to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bookmark {
+    pub instant: InstantIndex,
+    /// Possibly empty. At most `BOOKMARK_NAME_MAX_LEN` bytes long.
+    pub name: String,
+}
+
+/// Which coordinate a spacetime diagram plots against the instants.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Axis {
+    #[default]
+    X,
+    Y,
+}
+
+impl Axis {
+    /// How many different values the coordinate can have.
+    pub fn cell_count(self) -> u16 {
+        match self {
+            Axis::X => u16::from(xy::MAX_W_INNER) + 1,
+            Axis::Y => u16::from(xy::MAX_H_INNER) + 1,
+        }
+    }
+
+    pub fn coordinate(self, x: X, y: Y) -> u16 {
+        match self {
+            Axis::X => x.get().get(),
+            Axis::Y => y.get().get(),
+        }
+    }
+}
+
+/// A plot of where each self was at each instant, so that a self shows up as
+/// a line, and a jump shows up as the line stopping in one place and a new
+/// one starting in another.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpacetimeView {
+    pub axis: Axis,
+    /// The instant the view is scrolled to.
+    pub centre: InstantIndex,
+}
+
+/// How many instants back the trail behind a ghost goes.
+pub const GHOST_TRAIL_LEN: InstantIndex = 6;
+
+/// A past self, as seen at some instant, along with where it had just been,
+/// so it can be drawn moving along its worldline.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ghost {
+    pub self_id: SelfId,
+    pub colour: PaletteIndex,
+    pub x: X,
+    pub y: Y,
+    /// Where the ghost was at each of the instants before, most recent
+    /// first. This stops short at the instant the ghost jumped in at.
+    pub trail: Vec<(X, Y)>,
+}
+
+/// How ghosts are drawn while playing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GhostView {
+    /// The instant the ghosts are drawn at. This catches up with the shown
+    /// instant over a few frames, rather than all at once, so that when the
+    /// shown instant jumps, the ghosts glide along their worldlines to get
+    /// there instead of popping up somewhere else.
+    pub instant: InstantIndex,
+    pub hide_trails: bool,
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum TimeMode {
-    // TODO? a dev feature to skip the main menu? Maybe just a cli arg
     #[default]
     MainMenu,
     Flowing,
     Manipulating(TimeInput),
-    Collision(CollisionError)
+    Collision(CollisionError),
+    /// Looking through the branches, with the given one highlighted.
+    ChoosingBranch(BranchIndex),
+    Spacetime(SpacetimeView),
+    /// Typing in a name for the given bookmark.
+    NamingBookmark(BookmarkIndex),
+    /// Looking through the bookmarks, with the given one highlighted, to pick
+    /// one to set the time input to.
+    ChoosingBookmark(TimeInput, BookmarkIndex),
+    /// The player reached the goal of the current level.
+    Won,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct State {
     pub rng: Xs,
-    pub instants: [Instant; INSTANT_COUNT as _],
+    pub instants: Timeline,
+    pub budget: Budget,
     pub current: InstantIndex,
     pub player: Player,
     pub last_outcome: AdvanceOutcome,
     pub time_mode: TimeMode,
-}
-
-impl Default for State {
-    fn default() -> Self {
-        Self {
-            rng: Xs::default(),
-            instants: core::array::from_fn(|_| Instant::default()),
-            current: 0,
-            player: <_>::default(),
-            last_outcome: <_>::default(),
-            time_mode: <_>::default(),
-        }
-    }
+    /// Set when the player causes a paradox, so it can be backed out of.
+    pub rewind: Option<Rewind>,
+    pub rules: Rules,
+    pub branches: Branches,
+    /// The loop the player is currently on.
+    pub self_id: SelfId,
+    /// The highest `SelfId` handed out so far, across all branches.
+    pub last_self_id: SelfId,
+    /// In order of instant.
+    pub bookmarks: Vec<Bookmark>,
+    pub terrain: Terrain,
+    /// The level from `levels::all` being played, if any.
+    pub level: Option<LevelIndex>,
+    /// Not saved, so loading a save starts with nothing to undo.
+    pub history: History,
+    /// Not saved either, since it only affects how things look.
+    pub ghost_view: GhostView,
 }
 
 impl State {
     pub fn new(seed: Seed) -> Box<State> {
-        let mut rng = xs::from_seed(seed);
+        let mut output: Box<State> = <_>::default();
+
+        output.rng = xs::from_seed(seed);
+        output.return_to_menu();
+
+        output
+    }
+
+    /// Throws away the current game and goes back to the main menu, with a
+    /// fresh open grid to play on. The rules, the rng and the level picked
+    /// on the menu are kept.
+    pub fn return_to_menu(&mut self) {
+        let mut rng = self.rng;
 
         let x = xy::x(xs::range(&mut rng, 0..xy::MAX_W_INNER as u32) as xy::Inner);
         let y = xy::y(xs::range(&mut rng, 0..xy::MAX_H_INNER as u32) as xy::Inner);
 
-        let mut output: Box<State> = <_>::default();
-
-        output.rng = rng;
-        output.player = Player {
-            x,
-            y,
+        *self = State {
+            rng,
+            rules: self.rules,
+            level: self.level,
+            player: Player {
+                x,
+                y,
+            },
+            ..<_>::default()
         };
-
-        output
     }
 
     pub fn move_up(&mut self) {
-        self.player.y -= Y::ONE;
+        let mut y = self.player.y;
+        y -= Y::ONE;
+        self.move_to(self.player.x, y);
     }
     pub fn move_down(&mut self) {
-        self.player.y += Y::ONE;
+        let mut y = self.player.y;
+        y += Y::ONE;
+        self.move_to(self.player.x, y);
     }
     pub fn move_left(&mut self) {
-        self.player.x -= X::ONE;
+        let mut x = self.player.x;
+        x -= X::ONE;
+        self.move_to(x, self.player.y);
     }
     pub fn move_right(&mut self) {
-        self.player.x += X::ONE;
+        let mut x = self.player.x;
+        x += X::ONE;
+        self.move_to(x, self.player.y);
     }
 
-    pub fn advance_time(&mut self) {
-        self.check_collision();
+    /// Replaces the terrain, moving the player to the first floor cell, in
+    /// reading order, if they would otherwise be stuck in a wall or a door.
+    pub fn set_terrain(&mut self, terrain: Terrain) {
+        self.terrain = terrain;
 
-        match self.time_mode {
-            TimeMode::Flowing => {
-                self.last_outcome = self.advance_time_inner();
+        if matches!(self.terrain.get(self.player.x, self.player.y), Cell::Wall | Cell::Door(_)) {
+            if let Some((x, y, _)) = self.terrain.iter().find(|&(_, _, cell)| cell == Cell::Floor) {
+                self.player = Player { x, y };
             }
-            _ => {}
         }
     }
 
-    fn advance_time_inner(&mut self) -> AdvanceOutcome {
-        // If we are at the last index already
-        if u32::from(self.current) == (INSTANT_COUNT - 1) {
-            return AdvanceOutcome::OutOfInstants
+    /// Starts the given level from `levels::all` afresh, keeping the rules.
+    /// Does nothing if there is no such level.
+    pub fn start_level(&mut self, index: LevelIndex) {
+        if let Some(level) = levels::all().get(index as usize) {
+            self.load_level(level);
+            self.level = Some(index);
         }
-        let new_splats: &mut [Splat; SPLAT_COUNT as _] = &mut self.instants[self.current as usize].splats;
-        let one_past_last = &mut self.instants[self.current as usize].one_past_last;
+    }
 
-        // If we are at the last index already
-        if u16::from(*one_past_last) == SPLAT_COUNT - 1 {
-            return AdvanceOutcome::OutOfSplats
-        }
-        new_splats[*one_past_last as usize] = Splat {
-            x: self.player.x,
-            y: self.player.y,
-            colour: 6,
+    /// Throws away the timeline and everything else about the current game,
+    /// except for the rules and the rng, and sets up the given level.
+    pub fn load_level(&mut self, level: &Level) {
+        *self = State {
+            rng: self.rng,
+            rules: self.rules,
+            budget: level.budget,
+            current: level.start_instant,
+            player: level.start,
+            terrain: level.terrain.clone(),
+            time_mode: TimeMode::Flowing,
+            ..<_>::default()
         };
+    }
 
-        *one_past_last += 1;
+    /// Moves the player there, unless the terrain is in the way. Past selves
+    /// only ever went where the player could, so this keeps them out of
+    /// walls and closed doors too.
+    fn move_to(&mut self, x: X, y: Y) {
+        if !self.is_blocked(x, y) {
+            self.player.x = x;
+            self.player.y = y;
+        }
+    }
 
-        self.current += 1;
+    fn is_blocked(&self, x: X, y: Y) -> bool {
+        self.is_blocked_at(x, y, self.current)
+    }
 
-        AdvanceOutcome::Success
+    fn is_blocked_at(&self, x: X, y: Y, instant: InstantIndex) -> bool {
+        match self.terrain.get(x, y) {
+            Cell::Door(channel) => !self.is_door_open(channel, instant),
+            cell => cell.blocks(),
+        }
     }
 
-    // TODO? Make collision detection optional?
-    pub fn check_collision(&mut self) {
-        use TimeMode::*;
+    /// Moves one cell up or down and one cell left or right at once. The
+    /// player cannot squeeze between two blocked cells that only touch at
+    /// the corners, so if the way is blocked, they slide along whichever of
+    /// the two ways is open instead, trying left or right first.
+    fn move_diagonally(&mut self, up: bool, left: bool) {
+        let Player { x, y } = self.player;
+        let mut to_x = x;
+        let mut to_y = y;
+        if left {
+            to_x -= X::ONE;
+        } else {
+            to_x += X::ONE;
+        }
+        if up {
+            to_y -= Y::ONE;
+        } else {
+            to_y += Y::ONE;
+        }
 
-        match self.get_collision_info() {
-            Ok(()) => {
-                self.time_mode = Flowing;
-            },
-            Err(e) => {
-                self.time_mode = Collision(e);
-            },
+        let squeezing = self.is_blocked(to_x, y) && self.is_blocked(x, to_y);
+        if !squeezing && !self.is_blocked(to_x, to_y) {
+            self.move_to(to_x, to_y);
+        } else if !self.is_blocked(to_x, y) {
+            self.move_to(to_x, y);
+        } else {
+            self.move_to(x, to_y);
         }
     }
 
-    fn get_collision_info(&self) -> Result<(), CollisionError> {
-        for splat in self.current_non_player_splats() {
-            if self.player.x.get() == splat.x.get()
-            && self.player.y.get() == splat.y.get() {
-                return Err(CollisionError{})
-            }
+    /// Whether someone is standing on a plate on the given channel, at the
+    /// given instant. Only the past selves recorded at that instant count,
+    /// since the player cannot be on a plate and in the doorway at once.
+    pub fn is_door_open(&self, channel: Channel, instant: InstantIndex) -> bool {
+        self.instants.splats(instant).iter().any(|splat| {
+            self.terrain.get(splat.x, splat.y) == Cell::Plate(channel)
+        })
+    }
+
+    /// Moves in the given direction, if any, then advances time.
+    pub fn step(&mut self, dir: Option<Dir>) {
+        let before = (self.player, self.current, self.time_mode);
+        let self_id = self.self_id;
+
+        match dir {
+            Some(Dir::Up) => self.move_up(),
+            Some(Dir::Down) => self.move_down(),
+            Some(Dir::Left) => self.move_left(),
+            Some(Dir::Right) => self.move_right(),
+            Some(Dir::UpLeft) => self.move_diagonally(true, true),
+            Some(Dir::UpRight) => self.move_diagonally(true, false),
+            Some(Dir::DownLeft) => self.move_diagonally(false, true),
+            Some(Dir::DownRight) => self.move_diagonally(false, false),
+            None => {},
         }
 
-        Ok(())
-    }
+        self.advance_time();
 
-    fn current_non_player_splats(&self) -> &[Splat] {
-        use TimeMode::*;
-        let current = match self.time_mode {
-            MainMenu => {
-                return &[]
-            },
-            Flowing | Collision(_) => self.current,
-            Manipulating(ref time_input) => time_input.get_value(),
-        };
+        if let TimeMode::Collision(_) = self.time_mode {
+            let (player, current, time_mode) = before;
 
-        let instant: &Instant = &self.instants[current as usize];
+            self.rewind = Some(match dir {
+                Some(dir) => Rewind {
+                    action: Action::Move(dir),
+                    player,
+                    current,
+                    time_mode,
+                    self_id,
+                },
+                // Standing still is only a problem because of the previous
+                // step, so we go back to before that one.
+                None => Rewind {
+                    action: Action::Wait,
+                    player,
+                    current: current.saturating_sub(1),
+                    time_mode,
+                    self_id,
+                },
+            });
+        }
 
-        &instant.splats[0..instant.one_past_last as usize]
+        if self.time_mode == TimeMode::Flowing
+        && self.terrain.get(self.player.x, self.player.y) == Cell::Goal {
+            self.time_mode = TimeMode::Won;
+        }
     }
 
-    pub fn current_splats(&self) -> (&[Splat], Splat) {
-        use TimeMode::*;
-        (
-            self.current_non_player_splats(),
-            Splat {
-                x: self.player.x,
-                y: self.player.y,
-                colour: match (self.get_collision_info(), self.time_mode) {
-                    (Err(_), _) => 2,
-                    (Ok(()), MainMenu | Collision(_)) => 0,
-                    (Ok(()), Flowing) => 6,
-                    (Ok(()), Manipulating(_)) => 1,
-                },
-            }
-        )
+    /// Sets the current instant to `to`, as a new self, checking for a
+    /// paradox there.
+    pub fn jump_to(&mut self, to: InstantIndex) {
+        let (player, from, time_mode) = (self.player, self.current, self.time_mode);
+        let self_id = self.self_id;
+
+        self.last_self_id += 1;
+        self.self_id = self.last_self_id;
+        self.current = to;
+        // So the collision check looks at `to`, not at a `TimeInput`.
+        self.time_mode = TimeMode::Flowing;
+        self.check_collision();
+
+        if let TimeMode::Collision(_) = self.time_mode {
+            self.rewind = Some(Rewind {
+                action: Action::Jump { from, to },
+                player,
+                current: from,
+                time_mode,
+                self_id,
+            });
+            return
+        }
+
+        // Forking only once the jump is known not to cause a paradox means
+        // backing out of one never has a branch to clean up. The new branch
+        // starts as a copy of this one, so the check above comes out the
+        // same as it would have on the new branch.
+        if self.rules == Rules::Branching && to < from {
+            let new_self_id = self.self_id;
+            self.current = from;
+            self.self_id = self_id;
+
+            self.fork(to);
+
+            self.current = to;
+            self.self_id = new_self_id;
+        }
     }
 
-    pub fn fresh_time_input(&self) -> TimeInput {
-        TimeInput {
+    /// Creates a new branch that starts as a copy of the active one, and makes
+    /// it the active branch.
+    fn fork(&mut self, forked_at: InstantIndex) {
+        let parent = self.branches.active;
+
+        self.branches.list.push(Branch {
+            parent: Some(parent),
+            forked_at,
+            instants: self.instants.clone(),
             current: self.current,
-            initial: self.current,
+            player: self.player,
+            self_id: self.self_id,
+        });
+
+        self.activate_branch(self.branches.count() - 1);
+    }
+
+    /// Makes the branch at the given index the active one, picking up where
+    /// the player left off in it. This cannot be undone, and what was done
+    /// on the previous branch cannot be undone from this one, so it clears
+    /// the history.
+    pub fn switch_branch(&mut self, index: BranchIndex) {
+        if index != self.branches.active
+        && index < self.branches.count() {
+            self.history = <_>::default();
+        }
+
+        self.activate_branch(index);
+    }
+
+    fn activate_branch(&mut self, index: BranchIndex) {
+        if index == self.branches.active
+        || index >= self.branches.count() {
+            return
         }
+
+        let active = &mut self.branches.list[self.branches.active as usize];
+        core::mem::swap(&mut self.instants, &mut active.instants);
+        active.current = self.current;
+        active.player = self.player;
+        active.self_id = self.self_id;
+
+        let next = &mut self.branches.list[index as usize];
+        core::mem::swap(&mut self.instants, &mut next.instants);
+        self.current = next.current;
+        self.player = next.player;
+        self.self_id = next.self_id;
+
+        self.branches.active = index;
+    }
+
+    /// Backs out of the current paradox, if there is one, returning to how
+    /// things were before the action that caused it.
+    pub fn rewind(&mut self) {
+        let Some(rewind) = self.rewind.take() else {
+            return
+        };
+
+        match rewind.action {
+            Action::Wait => {
+                if rewind.current < self.current {
+                    self.instants.pop(rewind.current);
+                }
+            },
+            // The self the jump started never got to leave a splat.
+            Action::Jump { .. } => {
+                self.last_self_id -= 1;
+            },
+            Action::Move(_) => {},
+        }
+
+        self.player = rewind.player;
+        self.current = rewind.current;
+        self.time_mode = rewind.time_mode;
+        self.self_id = rewind.self_id;
+    }
+
+    /// Like `step`, but keeps track of moves in the history, so they can be
+    /// undone. Waits are not kept track of on their own, since time flows
+    /// all the time. Instead, they get undone along with the move or jump
+    /// before them.
+    pub fn step_undoably(&mut self, dir: Option<Dir>) {
+        let done = self.done(match dir {
+            Some(dir) => Action::Move(dir),
+            None => Action::Wait,
+        });
+
+        self.step(dir);
+
+        self.record(done);
+    }
+
+    /// Like `jump_to`, but keeps track of the jump in the history, so it can
+    /// be undone.
+    pub fn jump_to_undoably(&mut self, to: InstantIndex) {
+        let branch_count = self.branches.count();
+        let done = Done {
+            splats_from: to,
+            ..self.done(Action::Jump { from: self.current, to })
+        };
+
+        self.jump_to(to);
+
+        self.record(Done {
+            forked: self.branches.count() > branch_count,
+            ..done
+        });
+    }
+
+    fn done(&self, action: Action) -> Done {
+        Done {
+            action,
+            player: self.player,
+            current: self.current,
+            self_id: self.self_id,
+            last_self_id: self.last_self_id,
+            splats_from: self.current,
+            forked: false,
+        }
+    }
+
+    fn record(&mut self, done: Done) {
+        if let TimeMode::Collision(_) = self.time_mode {
+            // Backing out of the paradox can go back to before where the
+            // undone actions would be redone from.
+            self.history.undone.clear();
+            return
+        }
+
+        if done.action == Action::Wait
+        || (done.player, done.current, done.self_id) == (self.player, self.current, self.self_id) {
+            return
+        }
+
+        self.history.undone.clear();
+        self.history.done.push(done);
+    }
+
+    /// Takes back the last move or jump, along with any waiting since, if
+    /// there is one.
+    pub fn undo(&mut self) {
+        let Some(done) = self.history.done.pop() else {
+            return
+        };
+
+        self.history.undone.push(Undone {
+            action: done.action,
+            from: done.current,
+            until: self.current,
+        });
+
+        for instant in done.splats_from..self.current {
+            self.instants.pop(instant);
+        }
+
+        if done.forked {
+            let forked = self.branches.active;
+            if let Some(parent) = self.branches.list[forked as usize].parent {
+                self.activate_branch(parent);
+                // Any branches forked after this one were undone already.
+                self.branches.list.pop();
+            }
+        }
+
+        self.player = done.player;
+        self.current = done.current;
+        self.self_id = done.self_id;
+        self.last_self_id = done.last_self_id;
+        self.time_mode = TimeMode::Flowing;
+    }
+
+    /// Does the last undone move or jump again, if there is one, putting
+    /// things back how they were just before it was undone.
+    pub fn redo(&mut self) {
+        let Some(undone) = self.history.undone.pop() else {
+            return
+        };
+
+        // Time has flowed since the undo, so take that back first. Anything
+        // else would have cleared what there was to redo.
+        for instant in undone.from..self.current {
+            self.instants.pop(instant);
+        }
+        self.current = undone.from;
+
+        // Doing things afresh clears what there is to redo.
+        let undone_list = core::mem::take(&mut self.history.undone);
+
+        match undone.action {
+            Action::Move(dir) => self.step_undoably(Some(dir)),
+            Action::Jump { to, .. } => self.jump_to_undoably(to),
+            Action::Wait => {},
+        }
+
+        while self.current < undone.until
+        && self.time_mode == TimeMode::Flowing {
+            let before = self.current;
+            self.step_undoably(None);
+            if self.current == before {
+                break
+            }
+        }
+
+        if self.time_mode == TimeMode::Flowing {
+            self.history.undone = undone_list;
+        }
+    }
+
+    pub fn advance_time(&mut self) {
+        self.check_collision();
+
+        match self.time_mode {
+            TimeMode::Flowing => {
+                self.last_outcome = self.advance_time_inner();
+            }
+            _ => {}
+        }
+    }
+
+    fn advance_time_inner(&mut self) -> AdvanceOutcome {
+        // If we are at the last index already
+        if self.current >= self.budget.last_instant {
+            return AdvanceOutcome::OutOfInstants
+        }
+
+        // If the instant is already full
+        if self.instants.splats(self.current).len()
+            >= self.budget.splats_per_instant as usize {
+            return AdvanceOutcome::OutOfSplats
+        }
+
+        self.instants.push(
+            self.current,
+            Splat {
+                x: self.player.x,
+                y: self.player.y,
+                colour: self_colour(self.self_id),
+                self_id: self.self_id,
+            }
+        );
+
+        self.current += 1;
+
+        AdvanceOutcome::Success
+    }
+
+    pub fn check_collision(&mut self) {
+        use TimeMode::*;
+
+        match self.get_collision_info() {
+            Ok(()) => {
+                self.time_mode = Flowing;
+            },
+            Err(e) => {
+                self.time_mode = Collision(e);
+            },
+        }
+    }
+
+    /// The paradox the player would be in at the instant being shown, if any.
+    /// While manipulating time, that is the instant that would be jumped to.
+    pub fn pending_collision(&self) -> Option<CollisionError> {
+        self.get_collision_info().err()
+    }
+
+    fn get_collision_info(&self) -> Result<(), CollisionError> {
+        let Some(instant) = self.current_instant() else {
+            return Ok(())
+        };
+
+        if let Cell::Door(channel) = self.terrain.get(self.player.x, self.player.y) {
+            if !self.is_door_open(channel, instant) {
+                return Err(CollisionError {
+                    instant,
+                    x: self.player.x,
+                    y: self.player.y,
+                    splat_index: 0,
+                    kind: ParadoxKind::ShutIn,
+                })
+            }
+        }
+
+        if self.rules == Rules::Branching {
+            return Ok(())
+        }
+
+        for (splat_index, splat) in self.instants.splats(instant).iter().enumerate() {
+            if self.player.x == splat.x
+            && self.player.y == splat.y {
+                return Err(CollisionError {
+                    instant,
+                    x: splat.x,
+                    y: splat.y,
+                    splat_index: splat_index as SplatIndex,
+                    kind: ParadoxKind::SameCell,
+                })
+            }
+        }
+
+        if self.rules == Rules::SelfConsistent && instant == self.current {
+            self.check_recorded_moves(instant)?;
+        }
+
+        Ok(())
+    }
+
+    /// Replays the move each past self made from the instant before
+    /// `instant` to `instant`, and checks that the player, who just made a
+    /// move over the same span, did not get in its way. Moves are at most
+    /// one cell each way, so besides ending up in the same cell, which is
+    /// checked elsewhere, the player can only cross a past self's path, or
+    /// stand where it needed to squeeze past diagonally.
+    fn check_recorded_moves(&self, instant: InstantIndex) -> Result<(), CollisionError> {
+        let Some(previous) = instant.checked_sub(1) else {
+            return Ok(())
+        };
+
+        // If the player was not around in the previous instant, say right
+        // after a jump, then they cannot have been in anyone's way.
+        let Some((_, from)) = self.instants.self_splat(previous, self.self_id) else {
+            return Ok(())
+        };
+        let from = (from.x, from.y);
+        let to = (self.player.x, self.player.y);
+
+        for (splat_index, splat) in self.instants.splats(instant).iter().enumerate() {
+            let Some((_, ghost_from)) = self.instants.self_splat(previous, splat.self_id) else {
+                continue
+            };
+            let ghost_from = (ghost_from.x, ghost_from.y);
+            let ghost_to = (splat.x, splat.y);
+
+            let kind = if crosses(from, to, ghost_from, ghost_to) {
+                ParadoxKind::Crossed
+            } else if self.squeezes_past(ghost_from, ghost_to, to, previous) {
+                ParadoxKind::Blocked
+            } else {
+                continue
+            };
+
+            return Err(CollisionError {
+                instant,
+                x: self.player.x,
+                y: self.player.y,
+                splat_index: splat_index as SplatIndex,
+                kind,
+            })
+        }
+
+        Ok(())
+    }
+
+    /// Whether a diagonal move from `from` to `to`, made at `instant`, has
+    /// to squeeze between `player` and a blocked cell. That would not have
+    /// been allowed, so whoever made the move would have gone elsewhere.
+    fn squeezes_past(
+        &self,
+        from: (X, Y),
+        to: (X, Y),
+        player: (X, Y),
+        instant: InstantIndex,
+    ) -> bool {
+        if from.0 == to.0 || from.1 == to.1 {
+            return false
+        }
+
+        let corners = [(to.0, from.1), (from.0, to.1)];
+        let is_blocked = |(x, y): (X, Y)| {
+            (x, y) == player || self.is_blocked_at(x, y, instant)
+        };
+
+        corners.contains(&player) && corners.into_iter().all(is_blocked)
+    }
+
+    /// How many selves were around at the given instant, not counting the
+    /// player if they have not been there yet.
+    pub fn self_count_at(&self, instant: InstantIndex) -> usize {
+        self.instants.splats(instant).len()
+    }
+
+    /// Where the given self was at the given instant, if they were around
+    /// then.
+    pub fn self_position(&self, self_id: SelfId, instant: InstantIndex) -> Option<(X, Y)> {
+        self.instants
+            .self_splat(instant, self_id)
+            .map(|(_, splat)| (splat.x, splat.y))
+    }
+
+    /// The instant that is currently being shown, or the current one if
+    /// none is.
+    pub fn shown_instant(&self) -> InstantIndex {
+        self.current_instant().unwrap_or(self.current)
+    }
+
+    /// The instant that is currently being shown, if any.
+    fn current_instant(&self) -> Option<InstantIndex> {
+        use TimeMode::*;
+        match self.time_mode {
+            MainMenu => None,
+            Flowing
+            | Collision(_)
+            | ChoosingBranch(_)
+            | Spacetime(_)
+            | NamingBookmark(_)
+            | Won => Some(self.current),
+            Manipulating(ref time_input)
+            | ChoosingBookmark(ref time_input, _) => Some(time_input.get_value()),
+        }
+    }
+
+    fn current_non_player_splats(&self) -> &[Splat] {
+        match self.current_instant() {
+            Some(instant) => self.instants.splats(instant),
+            None => &[],
+        }
+    }
+
+    pub fn current_splats(&self) -> (&[Splat], Splat) {
+        use TimeMode::*;
+        (
+            self.current_non_player_splats(),
+            Splat {
+                x: self.player.x,
+                y: self.player.y,
+                colour: match (self.get_collision_info(), self.time_mode) {
+                    (Err(_), _) => 2,
+                    (Ok(()), MainMenu | Collision(_) | ChoosingBranch(_) | Spacetime(_)) => 0,
+                    (Ok(()), Flowing | NamingBookmark(_) | Won) => self_colour(self.self_id),
+                    (Ok(()), Manipulating(_) | ChoosingBookmark(..)) => 1,
+                },
+                self_id: self.self_id,
+            }
+        )
+    }
+
+    /// The past selves at the given instant, each with a trail of up to
+    /// `trail_len` earlier positions.
+    pub fn ghosts(&self, instant: InstantIndex, trail_len: InstantIndex) -> Vec<Ghost> {
+        self.instants
+            .splats(instant)
+            .iter()
+            .map(|splat| Ghost {
+                self_id: splat.self_id,
+                colour: splat.colour,
+                x: splat.x,
+                y: splat.y,
+                trail: (1..=trail_len)
+                    .map_while(|back| {
+                        let (_, past) = self.instants.self_splat(
+                            instant.checked_sub(back)?,
+                            splat.self_id,
+                        )?;
+                        Some((past.x, past.y))
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Moves `ghost_view.instant` part of the way towards the shown instant,
+    /// taking bigger steps the further away it is. Meant to be called once a
+    /// frame, which keeps it right on the current instant while time flows.
+    pub fn ease_ghosts(&mut self) {
+        let target = self.shown_instant();
+        let view = &mut self.ghost_view;
+        if self.time_mode == TimeMode::MainMenu {
+            view.instant = target;
+            return
+        }
+
+        let step = core::cmp::max(1, target.abs_diff(view.instant) / 4);
+        if view.instant < target {
+            view.instant += step;
+        } else if view.instant > target {
+            view.instant -= step;
+        }
+    }
+
+    /// Every splat in the given range of instants, along with the instant it
+    /// is at, in order of instant. The player is included at the current
+    /// instant, since they have not left a splat there yet.
+    pub fn spacetime_splats(
+        &self,
+        instants: core::ops::RangeInclusive<InstantIndex>,
+    ) -> impl Iterator<Item = (InstantIndex, Splat)> + '_ {
+        let player = instants.contains(&self.current).then(|| (
+            self.current,
+            Splat {
+                x: self.player.x,
+                y: self.player.y,
+                colour: self_colour(self.self_id),
+                self_id: self.self_id,
+            },
+        ));
+
+        self.instants
+            .range(instants)
+            .flat_map(|(index, splats)| splats.iter().map(move |&splat| (index, splat)))
+            .chain(player)
+    }
+
+    /// Bookmarks the current instant, with no name, and returns the index
+    /// the new bookmark ended up at.
+    pub fn add_bookmark(&mut self) -> BookmarkIndex {
+        let index = self.bookmarks.partition_point(|b| b.instant <= self.current);
+        self.bookmarks.insert(index, Bookmark {
+            instant: self.current,
+            name: String::new(),
+        });
+
+        index as BookmarkIndex
+    }
+
+    pub fn remove_bookmark(&mut self, index: BookmarkIndex) {
+        if (index as usize) < self.bookmarks.len() {
+            self.bookmarks.remove(index as usize);
+        }
+    }
+
+    pub fn fresh_time_input(&self) -> TimeInput {
+        TimeInput {
+            current: self.current,
+            initial: self.current,
+            max: self.budget.last_instant,
+            typing: false,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        save::to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Box<State>, save::Error> {
+        save::from_bytes(bytes)
+    }
+}
+
+pub type LevelIndex = u32;
+
+/// A puzzle: a grid to start on, and where and when to start on it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Level {
+    pub name: String,
+    pub terrain: Terrain,
+    pub start: Player,
+    pub start_instant: InstantIndex,
+    pub budget: Budget,
+}
+
+pub mod levels {
+    use super::*;
+
+    /// The text of the levels that come with the game, in the order they are
+    /// played. These are built into the game, so they are available on the
+    /// web too.
+    pub const EMBEDDED: [&str; 3] = [
+        include_str!("../levels/01-reach-the-goal.level"),
+        include_str!("../levels/02-hold-the-door.level"),
+        include_str!("../levels/03-two-doors.level"),
+    ];
+
+    /// The levels that come with the game, in the order they are played.
+    pub fn all() -> &'static [Level] {
+        static ALL: std::sync::OnceLock<Vec<Level>> = std::sync::OnceLock::new();
+
+        ALL.get_or_init(|| {
+            EMBEDDED.iter()
+                .map(|text| match level_file::parse(text) {
+                    Ok(level) => level,
+                    // The embedded levels are checked by a test, so this can
+                    // only happen if that test is failing.
+                    Err(e) => panic!("embedded level is invalid: {e}"),
+                })
+                .collect()
+        })
+    }
+}
+
+/// Levels as plain text, so they can be made without touching the code.
+///
+/// ```text
+/// ; Comment lines start with a semicolon, except in the grid.
+/// [level]
+/// name = hold the door
+/// start = 50, 34
+/// ; The rest of this section can be left out, to use the defaults.
+/// instant = 0
+/// last instant = 65535
+/// selves per instant = 255
+/// origin = 48, 28
+///
+/// [objects]
+/// plate 0 = 51, 30
+/// door 0 = 58, 32
+/// goal = 65, 32
+///
+/// [grid]
+/// #####################
+/// #.........#.........#
+/// ```
+///
+/// Coordinates are cells on the whole grid, with (0, 0) at the top left.
+/// A name with spaces at either end, or with control characters such as a
+/// newline, goes in double quotes, with `\n`, `\r`, `\t`, `\"`, `\\` and
+/// `\u{7f}` style escapes.
+/// The grid section is a picture of the walls, in the form that
+/// `Terrain::parse` takes, with its top left at the `origin` cell. A door
+/// is open while someone is on a plate with the same number.
+pub mod level_file {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub struct Error {
+        pub line: usize,
+        pub column: usize,
+        pub kind: ErrorKind,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum ErrorKind {
+        ExpectedSection,
+        UnknownSection(String),
+        DuplicateSection(String),
+        ExpectedKeyAndValue,
+        UnknownKey(String),
+        DuplicateKey(String),
+        MissingKey(&'static str),
+        InvalidNumber(String),
+        ExpectedCoordinates,
+        OffTheGrid,
+        StartBlocked,
+        StartsPastLastInstant,
+        InvalidName,
+        TooManyRows,
+        RowTooLong,
+        UnknownCell(char),
+    }
+
+    impl core::fmt::Display for Error {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            use ErrorKind::*;
+            write!(f, "line {}, column {}: ", self.line, self.column)?;
+            match &self.kind {
+                ExpectedSection => write!(f, "expected a section, like `[level]`"),
+                UnknownSection(name) => write!(
+                    f,
+                    "unknown section `[{name}]`; expected `[level]`, `[objects]` or `[grid]`"
+                ),
+                DuplicateSection(name) => write!(f, "there is already a `[{name}]` section"),
+                ExpectedKeyAndValue => write!(f, "expected a line like `key = value`"),
+                UnknownKey(key) => write!(f, "unknown key `{key}`"),
+                DuplicateKey(key) => write!(f, "`{key}` is already set"),
+                MissingKey(key) => write!(f, "the level section needs a `{key}`"),
+                InvalidNumber(text) => write!(f, "`{text}` is not a valid number here"),
+                ExpectedCoordinates => write!(f, "expected coordinates, like `12, 34`"),
+                OffTheGrid => write!(
+                    f,
+                    "the grid only goes up to {}, {}",
+                    xy::MAX_W_INNER,
+                    xy::MAX_H_INNER,
+                ),
+                StartBlocked => write!(f, "the start is inside a wall or a door"),
+                StartsPastLastInstant => write!(f, "the level starts after its last instant"),
+                InvalidName => write!(
+                    f,
+                    "expected a name in double quotes, with only `\\n`, `\\r`, `\\t`, `\\\"`, `\\\\` or `\\u{{...}}` escapes"
+                ),
+                TooManyRows => write!(f, "the grid is only {TERRAIN_H} rows high"),
+                RowTooLong => write!(f, "the grid is only {TERRAIN_W} cells wide"),
+                UnknownCell(character) => write!(
+                    f,
+                    "{character:?} is not a cell; expected `#`, `.` or a space"
+                ),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    /// Where a part of a line starts, counting from 1.
+    fn column_of(line: &str, part: &str) -> usize {
+        let offset = part.as_ptr() as usize - line.as_ptr() as usize;
+        line[..offset].chars().count() + 1
+    }
+
+    fn number<N: core::str::FromStr>(line_number: usize, line: &str, text: &str) -> Result<N, Error> {
+        text.parse().map_err(|_| Error {
+            line: line_number,
+            column: column_of(line, text),
+            kind: ErrorKind::InvalidNumber(text.to_string()),
+        })
+    }
+
+    fn coordinates(line_number: usize, line: &str, text: &str) -> Result<(X, Y), Error> {
+        let Some((x_text, y_text)) = text.split_once(',') else {
+            return Err(Error {
+                line: line_number,
+                column: column_of(line, text),
+                kind: ErrorKind::ExpectedCoordinates,
+            })
+        };
+        let (x_text, y_text) = (x_text.trim(), y_text.trim());
+
+        let x: xy::Inner = number(line_number, line, x_text)?;
+        let y: xy::Inner = number(line_number, line, y_text)?;
+        if x > xy::MAX_W_INNER || y > xy::MAX_H_INNER {
+            return Err(Error {
+                line: line_number,
+                column: column_of(line, text),
+                kind: ErrorKind::OffTheGrid,
+            })
+        }
+
+        Ok((xy::x(x), xy::y(y)))
+    }
+
+    /// Whether a name has to go in quotes to come back the same from `parse`.
+    fn needs_quotes(name: &str) -> bool {
+        name.trim() != name
+        || name.starts_with('"')
+        || name.chars().any(char::is_control)
+    }
+
+    fn quote(name: &str) -> String {
+        let mut output = String::from('"');
+        for character in name.chars() {
+            match character {
+                '\n' => output.push_str("\\n"),
+                '\r' => output.push_str("\\r"),
+                '\t' => output.push_str("\\t"),
+                '"' => output.push_str("\\\""),
+                '\\' => output.push_str("\\\\"),
+                _ if character.is_control() => output.push_str(&format!("\\u{{{:x}}}", character as u32)),
+                _ => output.push(character),
+            }
+        }
+        output.push('"');
+        output
+    }
+
+    /// The name `quote` was given, if `quoted` came from it.
+    fn unquote(quoted: &str) -> Option<String> {
+        let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+
+        let mut output = String::new();
+        let mut chars = inner.chars();
+        while let Some(character) = chars.next() {
+            output.push(match character {
+                '"' => return None,
+                '\\' => match chars.next()? {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '"' => '"',
+                    '\\' => '\\',
+                    'u' => {
+                        let rest = chars.as_str().strip_prefix('{')?;
+                        let (hex, rest) = rest.split_once('}')?;
+                        chars = rest.chars();
+                        char::from_u32(u32::from_str_radix(hex, 16).ok()?)?
+                    },
+                    _ => return None,
+                },
+                _ => character,
+            });
+        }
+
+        Some(output)
+    }
+
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Section {
+        Level,
+        Objects,
+        Grid,
+    }
+
+    pub fn parse(text: &str) -> Result<Level, Error> {
+        let mut section = None;
+        let mut seen_sections = Vec::new();
+        let mut level_line = 1;
+        let mut seen_keys: Vec<String> = Vec::new();
+
+        let mut name = None;
+        // The line number and column of the start, for if it turns out to
+        // be blocked.
+        let mut start = None;
+        let mut start_instant = None;
+        let mut last_instant = None;
+        let mut splats_per_instant = None;
+        let mut origin = None;
+        let mut objects = Vec::new();
+        // The first line of the grid, and the grid itself.
+        let mut grid: Option<(usize, String)> = None;
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let trimmed = line.trim();
+
+            if let Some(header) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                let header = header.trim();
+                let new_section = match header {
+                    "level" => Section::Level,
+                    "objects" => Section::Objects,
+                    "grid" => Section::Grid,
+                    _ => return Err(Error {
+                        line: line_number,
+                        column: column_of(line, header),
+                        kind: ErrorKind::UnknownSection(header.to_string()),
+                    }),
+                };
+                if seen_sections.contains(&new_section) {
+                    return Err(Error {
+                        line: line_number,
+                        column: column_of(line, header),
+                        kind: ErrorKind::DuplicateSection(header.to_string()),
+                    })
+                }
+                seen_sections.push(new_section);
+
+                match new_section {
+                    Section::Level => level_line = line_number,
+                    Section::Grid => grid = Some((line_number + 1, String::new())),
+                    Section::Objects => {},
+                }
+
+                section = Some(new_section);
+                continue
+            }
+
+            // The grid is taken as is, so a comment there would be a row.
+            if let (Some(Section::Grid), Some((_, picture))) = (section, &mut grid) {
+                picture.push_str(line);
+                picture.push('\n');
+                continue
+            }
+
+            if trimmed.is_empty() || trimmed.starts_with(';') {
+                continue
+            }
+
+            let Some(section) = section else {
+                return Err(Error {
+                    line: line_number,
+                    column: column_of(line, trimmed),
+                    kind: ErrorKind::ExpectedSection,
+                })
+            };
+
+            let Some((key, value)) = trimmed.split_once('=') else {
+                return Err(Error {
+                    line: line_number,
+                    column: column_of(line, trimmed),
+                    kind: ErrorKind::ExpectedKeyAndValue,
+                })
+            };
+            let (key, value) = (key.trim(), value.trim());
+            // Normalize the spacing, so `plate  0` and `plate 0` are the same
+            // key.
+            let normalized = key.split_whitespace().collect::<Vec<_>>().join(" ");
+
+            let key_error = |kind| Error {
+                line: line_number,
+                column: column_of(line, key),
+                kind,
+            };
+
+            if seen_keys.contains(&normalized) {
+                return Err(key_error(ErrorKind::DuplicateKey(normalized)));
+            }
+
+            match section {
+                Section::Level => match normalized.as_str() {
+                    "name" if value.starts_with('"') => name = Some(unquote(value).ok_or(Error {
+                        line: line_number,
+                        column: column_of(line, value),
+                        kind: ErrorKind::InvalidName,
+                    })?),
+                    "name" => name = Some(value.to_string()),
+                    "start" => start = Some((
+                        coordinates(line_number, line, value)?,
+                        line_number,
+                        column_of(line, value),
+                    )),
+                    "instant" => start_instant = Some((
+                        number(line_number, line, value)?,
+                        line_number,
+                        column_of(line, value),
+                    )),
+                    "last instant" => last_instant = Some(number(line_number, line, value)?),
+                    "selves per instant" => splats_per_instant = Some(number(line_number, line, value)?),
+                    "origin" => origin = Some(coordinates(line_number, line, value)?),
+                    _ => return Err(key_error(ErrorKind::UnknownKey(key.to_string()))),
+                },
+                Section::Objects => {
+                    let mut words = key.split_whitespace();
+                    let object = match (words.next(), words.next(), words.next()) {
+                        (Some("goal"), None, None) => Cell::Goal,
+                        (Some(kind @ ("plate" | "door")), Some(channel), None) => {
+                            let channel = number(line_number, line, channel)?;
+                            if kind == "plate" {
+                                Cell::Plate(channel)
+                            } else {
+                                Cell::Door(channel)
+                            }
+                        },
+                        _ => return Err(key_error(ErrorKind::UnknownKey(key.to_string()))),
+                    };
+                    let (x, y) = coordinates(line_number, line, value)?;
+                    // The same object can be in more than one place.
+                    objects.push((x, y, object));
+                    continue
+                },
+                Section::Grid => unreachable!("grid lines are handled above"),
+            }
+
+            seen_keys.push(normalized);
+        }
+
+        let missing = |key| Error {
+            line: level_line,
+            column: 1,
+            kind: ErrorKind::MissingKey(key),
+        };
+        let name = name.ok_or_else(|| missing("name"))?;
+        let ((start_x, start_y), start_line, start_column) = start.ok_or_else(|| missing("start"))?;
+        let (left, top) = origin.unwrap_or_default();
+
+        let terrain_error = |grid_line: usize, e: TerrainError| {
+            let (line, column, kind) = match e {
+                TerrainError::TooManyRows { line } => (line, 1, ErrorKind::TooManyRows),
+                TerrainError::RowTooLong { line, column } => (line, column, ErrorKind::RowTooLong),
+                TerrainError::UnknownCell { line, column, character } => (
+                    line,
+                    column,
+                    ErrorKind::UnknownCell(character),
+                ),
+            };
+            Error { line: grid_line + line - 1, column, kind }
+        };
+
+        let mut terrain = match grid {
+            Some((grid_line, picture)) => Terrain::parse_at(
+                &picture,
+                left.get().get() as xy::Inner,
+                top.get().get() as xy::Inner,
+            ).map_err(|e| terrain_error(grid_line, e))?,
+            None => Terrain::default(),
+        };
+        for (x, y, object) in objects {
+            terrain.set(x, y, object);
+        }
+
+        if matches!(terrain.get(start_x, start_y), Cell::Wall | Cell::Door(_)) {
+            return Err(Error {
+                line: start_line,
+                column: start_column,
+                kind: ErrorKind::StartBlocked,
+            })
+        }
+
+        let default_budget = Budget::default();
+        let last_instant = last_instant.unwrap_or(default_budget.last_instant);
+
+        let (start_instant, instant_line, instant_column) = start_instant.unwrap_or((0, level_line, 1));
+        if start_instant > last_instant {
+            return Err(Error {
+                line: instant_line,
+                column: instant_column,
+                kind: ErrorKind::StartsPastLastInstant,
+            })
+        }
+
+        Ok(Level {
+            name,
+            terrain,
+            start: Player { x: start_x, y: start_y },
+            start_instant,
+            budget: Budget {
+                last_instant,
+                splats_per_instant: splats_per_instant.unwrap_or(default_budget.splats_per_instant),
+            },
+        })
+    }
+
+    /// Writes the level out in the form `parse` takes, such that parsing it
+    /// gives back the same level.
+    pub fn write(level: &Level) -> String {
+        let mut walls = Vec::new();
+        let mut objects = Vec::new();
+        for (x, y, cell) in level.terrain.iter() {
+            let (x, y) = (x.get().get(), y.get().get());
+            match cell {
+                Cell::Floor => {},
+                Cell::Wall => walls.push((x, y)),
+                Cell::Plate(_) | Cell::Door(_) | Cell::Goal => objects.push((x, y, cell)),
+            }
+        }
+
+        // Start the picture at the top left wall, so there is no need for
+        // lots of empty rows and columns.
+        let left = walls.iter().map(|&(x, _)| x).min().unwrap_or(0);
+        let top = walls.iter().map(|&(_, y)| y).min().unwrap_or(0);
+
+        let mut output = String::new();
+        output.push_str("[level]\n");
+        if needs_quotes(&level.name) {
+            output.push_str(&format!("name = {}\n", quote(&level.name)));
+        } else {
+            output.push_str(&format!("name = {}\n", level.name));
+        }
+        output.push_str(&format!(
+            "start = {}, {}\n",
+            level.start.x.get().get(),
+            level.start.y.get().get(),
+        ));
+        output.push_str(&format!("instant = {}\n", level.start_instant));
+        output.push_str(&format!("last instant = {}\n", level.budget.last_instant));
+        output.push_str(&format!("selves per instant = {}\n", level.budget.splats_per_instant));
+        output.push_str(&format!("origin = {left}, {top}\n"));
+
+        if !objects.is_empty() {
+            output.push_str("\n[objects]\n");
+            for (x, y, cell) in objects {
+                match cell {
+                    Cell::Plate(channel) => output.push_str(&format!("plate {channel} = {x}, {y}\n")),
+                    Cell::Door(channel) => output.push_str(&format!("door {channel} = {x}, {y}\n")),
+                    Cell::Goal => output.push_str(&format!("goal = {x}, {y}\n")),
+                    Cell::Floor | Cell::Wall => {},
+                }
+            }
+        }
+
+        if let Some(bottom) = walls.iter().map(|&(_, y)| y).max() {
+            output.push_str("\n[grid]\n");
+            for y in top..=bottom {
+                let row: Vec<u16> = walls.iter()
+                    .filter(|&&(_, wall_y)| wall_y == y)
+                    .map(|&(x, _)| x)
+                    .collect();
+                let right = row.iter().copied().max().unwrap_or(left);
+                for x in left..=right {
+                    output.push(if row.contains(&x) { '#' } else { '.' });
+                }
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+}
+
+pub mod save {
+    use super::*;
+
+    pub const MAGIC: [u8; 4] = *b"TTSS";
+    pub const VERSION: u16 = 13;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Error {
+        NotASave,
+        UnsupportedVersion(u16),
+        UnexpectedEnd,
+        InvalidValue { offset: usize },
+        TrailingBytes { offset: usize },
+    }
+
+    impl core::fmt::Display for Error {
+        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+            use Error::*;
+            match self {
+                NotASave => write!(f, "not a time-travel-sim save"),
+                UnsupportedVersion(version) => write!(
+                    f,
+                    "save version {version} is not supported (expected {VERSION})"
+                ),
+                UnexpectedEnd => write!(f, "save ended unexpectedly"),
+                InvalidValue { offset } => write!(f, "invalid value at byte {offset}"),
+                TrailingBytes { offset } => write!(f, "unexpected extra bytes starting at byte {offset}"),
+            }
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    mod tag {
+        pub const SUCCESS: u8 = 0;
+        pub const OUT_OF_INSTANTS: u8 = 1;
+        pub const OUT_OF_SPLATS: u8 = 2;
+
+        pub const MAIN_MENU: u8 = 0;
+        pub const FLOWING: u8 = 1;
+        pub const MANIPULATING: u8 = 2;
+        pub const COLLISION: u8 = 3;
+        pub const CHOOSING_BRANCH: u8 = 4;
+        pub const SPACETIME: u8 = 5;
+        pub const NAMING_BOOKMARK: u8 = 6;
+        pub const CHOOSING_BOOKMARK: u8 = 7;
+        pub const WON: u8 = 8;
+
+        pub const AXIS_X: u8 = 0;
+        pub const AXIS_Y: u8 = 1;
+
+        pub const PARADOX: u8 = 0;
+        pub const BRANCHING: u8 = 1;
+        pub const SELF_CONSISTENT: u8 = 2;
+
+        pub const SAME_CELL: u8 = 0;
+        pub const CROSSED: u8 = 1;
+        pub const SHUT_IN: u8 = 2;
+        pub const BLOCKED: u8 = 3;
+
+        pub const NONE: u8 = 0;
+        pub const SOME: u8 = 1;
+
+        pub const WAIT: u8 = 0;
+        pub const MOVE: u8 = 1;
+        pub const JUMP: u8 = 2;
+
+        pub const FLOOR: u8 = 0;
+        pub const WALL: u8 = 1;
+        pub const PLATE: u8 = 2;
+        pub const DOOR: u8 = 3;
+        pub const GOAL: u8 = 4;
+
+        pub const UP: u8 = 0;
+        pub const DOWN: u8 = 1;
+        pub const LEFT: u8 = 2;
+        pub const RIGHT: u8 = 3;
+        pub const UP_LEFT: u8 = 4;
+        pub const UP_RIGHT: u8 = 5;
+        pub const DOWN_LEFT: u8 = 6;
+        pub const DOWN_RIGHT: u8 = 7;
+    }
+
+    pub fn to_bytes(state: &State) -> Vec<u8> {
+        let mut w = Writer::default();
+
+        w.bytes.extend_from_slice(&MAGIC);
+        w.bytes.extend_from_slice(&VERSION.to_le_bytes());
+
+        for wrapping in state.rng {
+            w.bytes.extend_from_slice(&wrapping.0.to_le_bytes());
+        }
+
+        w.varint(state.budget.last_instant);
+        w.varint(state.budget.splats_per_instant);
+
+        w.varint(state.current);
+        w.xy(state.player.x, state.player.y);
+
+        w.bytes.push(match state.last_outcome {
+            AdvanceOutcome::Success => tag::SUCCESS,
+            AdvanceOutcome::OutOfInstants => tag::OUT_OF_INSTANTS,
+            AdvanceOutcome::OutOfSplats => tag::OUT_OF_SPLATS,
+        });
+
+        w.time_mode(state.time_mode);
+
+        match state.rewind {
+            None => w.bytes.push(tag::NONE),
+            Some(Rewind { action, player, current, time_mode, self_id }) => {
+                w.bytes.push(tag::SOME);
+                match action {
+                    Action::Wait => w.bytes.push(tag::WAIT),
+                    Action::Move(dir) => {
+                        w.bytes.push(tag::MOVE);
+                        w.bytes.push(match dir {
+                            Dir::Up => tag::UP,
+                            Dir::Down => tag::DOWN,
+                            Dir::Left => tag::LEFT,
+                            Dir::Right => tag::RIGHT,
+                            Dir::UpLeft => tag::UP_LEFT,
+                            Dir::UpRight => tag::UP_RIGHT,
+                            Dir::DownLeft => tag::DOWN_LEFT,
+                            Dir::DownRight => tag::DOWN_RIGHT,
+                        });
+                    },
+                    Action::Jump { from, to } => {
+                        w.bytes.push(tag::JUMP);
+                        w.varint(from);
+                        w.varint(to);
+                    },
+                }
+                w.xy(player.x, player.y);
+                w.varint(current);
+                w.time_mode(time_mode);
+                w.varint(self_id);
+            },
+        }
+
+        w.timeline(&state.instants);
+
+        w.bytes.push(match state.rules {
+            Rules::Paradox => tag::PARADOX,
+            Rules::Branching => tag::BRANCHING,
+            Rules::SelfConsistent => tag::SELF_CONSISTENT,
+        });
+        w.varint(state.self_id);
+        w.varint(state.last_self_id);
+
+        w.varint(state.branches.active);
+        w.varint(state.branches.count());
+        for branch in &state.branches.list {
+            match branch.parent {
+                None => w.bytes.push(tag::NONE),
+                Some(parent) => {
+                    w.bytes.push(tag::SOME);
+                    w.varint(parent);
+                },
+            }
+            w.varint(branch.forked_at);
+            w.timeline(&branch.instants);
+            w.varint(branch.current);
+            w.xy(branch.player.x, branch.player.y);
+            w.varint(branch.self_id);
+        }
+
+        w.varint(state.bookmarks.len() as u32);
+        for bookmark in &state.bookmarks {
+            w.varint(bookmark.instant);
+            w.varint(bookmark.name.len() as u32);
+            w.bytes.extend_from_slice(bookmark.name.as_bytes());
+        }
+
+        w.terrain(&state.terrain);
+
+        match state.level {
+            None => w.bytes.push(tag::NONE),
+            Some(level) => {
+                w.bytes.push(tag::SOME);
+                w.varint(level);
+            },
+        }
+
+        w.bytes
+    }
+
+    #[derive(Default)]
+    struct Writer {
+        bytes: Vec<u8>,
+    }
+
+    impl Writer {
+        /// LEB128, so small numbers take up a single byte.
+        fn varint(&mut self, mut n: u32) {
+            loop {
+                let byte = (n & 0x7F) as u8;
+                n >>= 7;
+                if n == 0 {
+                    self.bytes.push(byte);
+                    break
+                }
+                self.bytes.push(byte | 0x80);
+            }
+        }
+
+        fn xy(&mut self, x: X, y: Y) {
+            // The casts are lossless, since `X` and `Y` are wrapped `xy::Inner`s.
+            self.bytes.push(x.get().0 as xy::Inner);
+            self.bytes.push(y.get().0 as xy::Inner);
+        }
+
+        fn timeline(&mut self, timeline: &Timeline) {
+            self.varint(timeline.used_count() as u32);
+
+            // Instants are written in ascending order, so we store the gaps
+            // between them, which are usually small.
+            let mut previous_index = 0;
+            for (index, splats) in timeline.iter() {
+                self.varint(index - previous_index);
+                previous_index = index;
+
+                self.varint(splats.len() as u32);
+                for splat in splats {
+                    self.xy(splat.x, splat.y);
+                    self.bytes.push(splat.colour);
+                    self.varint(splat.self_id);
+                }
+            }
+        }
+
+        fn time_mode(&mut self, time_mode: TimeMode) {
+            match time_mode {
+                TimeMode::MainMenu => self.bytes.push(tag::MAIN_MENU),
+                TimeMode::Flowing => self.bytes.push(tag::FLOWING),
+                TimeMode::Manipulating(time_input) => {
+                    self.bytes.push(tag::MANIPULATING);
+                    self.time_input(time_input);
+                },
+                TimeMode::Collision(CollisionError { instant, x, y, splat_index, kind }) => {
+                    self.bytes.push(tag::COLLISION);
+                    self.varint(instant);
+                    self.xy(x, y);
+                    self.varint(splat_index);
+                    self.bytes.push(match kind {
+                        ParadoxKind::SameCell => tag::SAME_CELL,
+                        ParadoxKind::Crossed => tag::CROSSED,
+                        ParadoxKind::ShutIn => tag::SHUT_IN,
+                        ParadoxKind::Blocked => tag::BLOCKED,
+                    });
+                },
+                TimeMode::ChoosingBranch(index) => {
+                    self.bytes.push(tag::CHOOSING_BRANCH);
+                    self.varint(index);
+                },
+                TimeMode::Spacetime(SpacetimeView { axis, centre }) => {
+                    self.bytes.push(tag::SPACETIME);
+                    self.bytes.push(match axis {
+                        Axis::X => tag::AXIS_X,
+                        Axis::Y => tag::AXIS_Y,
+                    });
+                    self.varint(centre);
+                },
+                TimeMode::NamingBookmark(index) => {
+                    self.bytes.push(tag::NAMING_BOOKMARK);
+                    self.varint(index);
+                },
+                TimeMode::ChoosingBookmark(time_input, index) => {
+                    self.bytes.push(tag::CHOOSING_BOOKMARK);
+                    self.time_input(time_input);
+                    self.varint(index);
+                },
+                TimeMode::Won => self.bytes.push(tag::WON),
+            }
+        }
+
+        /// Runs of identical cells, so mostly empty grids stay small. An
+        /// all floor grid is written as no runs at all.
+        fn terrain(&mut self, terrain: &Terrain) {
+            let mut runs: Vec<(u32, Cell)> = Vec::new();
+            for cell in terrain.cells.iter().copied() {
+                match runs.last_mut() {
+                    Some((len, previous)) if *previous == cell => *len += 1,
+                    _ => runs.push((1, cell)),
+                }
+            }
+
+            if let [(_, Cell::Floor)] = runs[..] {
+                runs.clear();
+            }
+
+            self.varint(runs.len() as u32);
+            for (len, cell) in runs {
+                self.varint(len);
+                match cell {
+                    Cell::Floor => self.bytes.push(tag::FLOOR),
+                    Cell::Wall => self.bytes.push(tag::WALL),
+                    Cell::Plate(channel) => {
+                        self.bytes.push(tag::PLATE);
+                        self.bytes.push(channel);
+                    },
+                    Cell::Door(channel) => {
+                        self.bytes.push(tag::DOOR);
+                        self.bytes.push(channel);
+                    },
+                    Cell::Goal => self.bytes.push(tag::GOAL),
+                }
+            }
+        }
+
+        fn time_input(&mut self, TimeInput { current, initial, max, typing }: TimeInput) {
+            self.varint(current);
+            self.varint(initial);
+            self.varint(max);
+            self.bytes.push(u8::from(typing));
+        }
+    }
+
+    struct Reader<'bytes> {
+        bytes: &'bytes [u8],
+        offset: usize,
+    }
+
+    impl <'bytes> Reader<'bytes> {
+        fn array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+            let end = self.offset + N;
+            let slice = self.bytes.get(self.offset..end)
+                .ok_or(Error::UnexpectedEnd)?;
+            self.offset = end;
+
+            let mut output = [0; N];
+            output.copy_from_slice(slice);
+            Ok(output)
+        }
+
+        fn u8(&mut self) -> Result<u8, Error> {
+            self.array::<1>().map(|[byte]| byte)
+        }
+
+        fn u16(&mut self) -> Result<u16, Error> {
+            self.array().map(u16::from_le_bytes)
+        }
+
+        fn u32(&mut self) -> Result<u32, Error> {
+            self.array().map(u32::from_le_bytes)
+        }
+
+        fn varint(&mut self) -> Result<u32, Error> {
+            let mut output: u32 = 0;
+            for shift in (0..32).step_by(7) {
+                let byte = self.u8()?;
+                let bits = u32::from(byte & 0x7F);
+                if shift == 28 && bits > 0xF {
+                    return Err(self.invalid());
+                }
+                output |= bits << shift;
+
+                if byte & 0x80 == 0 {
+                    return Ok(output)
+                }
+            }
+
+            Err(self.invalid())
+        }
+
+        fn invalid(&self) -> Error {
+            Error::InvalidValue { offset: self.offset.saturating_sub(1) }
+        }
+
+        fn xy(&mut self) -> Result<(X, Y), Error> {
+            let x = self.u8()?;
+            if x > xy::MAX_W_INNER {
+                return Err(self.invalid());
+            }
+            let y = self.u8()?;
+            if y > xy::MAX_H_INNER {
+                return Err(self.invalid());
+            }
+
+            Ok((xy::x(x), xy::y(y)))
+        }
+
+        fn timeline(&mut self) -> Result<Timeline, Error> {
+            let mut output = Timeline::default();
+
+            let used_count = self.varint()?;
+            let mut index: InstantIndex = 0;
+            for i in 0..used_count {
+                let gap = self.varint()?;
+                // Every instant after the first must come strictly after the
+                // previous one, which also rules out duplicates.
+                if i > 0 && gap == 0 {
+                    return Err(self.invalid());
+                }
+                index = index.checked_add(gap).ok_or_else(|| self.invalid())?;
+
+                let splat_count = self.varint()?;
+                if splat_count == 0 {
+                    return Err(self.invalid());
+                }
+
+                for _ in 0..splat_count {
+                    let (x, y) = self.xy()?;
+                    let colour = self.u8()?;
+                    let self_id = self.varint()?;
+                    output.push(index, Splat { x, y, colour, self_id });
+                }
+            }
+
+            Ok(output)
+        }
+
+        fn time_mode(&mut self) -> Result<TimeMode, Error> {
+            Ok(match self.u8()? {
+                tag::MAIN_MENU => TimeMode::MainMenu,
+                tag::FLOWING => TimeMode::Flowing,
+                tag::MANIPULATING => TimeMode::Manipulating(self.time_input()?),
+                tag::COLLISION => {
+                    let instant = self.varint()?;
+                    let (x, y) = self.xy()?;
+
+                    let splat_index = self.varint()?;
+                    let kind = match self.u8()? {
+                        tag::SAME_CELL => ParadoxKind::SameCell,
+                        tag::CROSSED => ParadoxKind::Crossed,
+                        tag::SHUT_IN => ParadoxKind::ShutIn,
+                        tag::BLOCKED => ParadoxKind::Blocked,
+                        _ => return Err(self.invalid()),
+                    };
+
+                    TimeMode::Collision(CollisionError {
+                        instant,
+                        x,
+                        y,
+                        splat_index,
+                        kind,
+                    })
+                },
+                tag::CHOOSING_BRANCH => TimeMode::ChoosingBranch(self.varint()?),
+                tag::SPACETIME => {
+                    let axis = match self.u8()? {
+                        tag::AXIS_X => Axis::X,
+                        tag::AXIS_Y => Axis::Y,
+                        _ => return Err(self.invalid()),
+                    };
+
+                    TimeMode::Spacetime(SpacetimeView {
+                        axis,
+                        centre: self.varint()?,
+                    })
+                },
+                tag::NAMING_BOOKMARK => TimeMode::NamingBookmark(self.varint()?),
+                tag::CHOOSING_BOOKMARK => TimeMode::ChoosingBookmark(
+                    self.time_input()?,
+                    self.varint()?,
+                ),
+                tag::WON => TimeMode::Won,
+                _ => return Err(self.invalid()),
+            })
+        }
+
+        fn terrain(&mut self) -> Result<Terrain, Error> {
+            let mut cells = Vec::new();
+
+            let run_count = self.varint()?;
+            for _ in 0..run_count {
+                let len = self.varint()? as usize;
+                if len == 0 || cells.len() + len > TERRAIN_CELL_COUNT {
+                    return Err(self.invalid());
+                }
+                let cell = match self.u8()? {
+                    tag::FLOOR => Cell::Floor,
+                    tag::WALL => Cell::Wall,
+                    tag::PLATE => Cell::Plate(self.u8()?),
+                    tag::DOOR => Cell::Door(self.u8()?),
+                    tag::GOAL => Cell::Goal,
+                    _ => return Err(self.invalid()),
+                };
+                cells.resize(cells.len() + len, cell);
+            }
+
+            if run_count > 0 && cells.len() != TERRAIN_CELL_COUNT {
+                return Err(self.invalid());
+            }
+
+            Ok(Terrain { cells })
+        }
+
+        fn time_input(&mut self) -> Result<TimeInput, Error> {
+            Ok(TimeInput {
+                current: self.varint()?,
+                initial: self.varint()?,
+                max: self.varint()?,
+                typing: match self.u8()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(self.invalid()),
+                },
+            })
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Box<State>, Error> {
+        let mut reader = Reader { bytes, offset: 0 };
+
+        if reader.array::<4>() != Ok(MAGIC) {
+            return Err(Error::NotASave);
+        }
+
+        let version = reader.u16()?;
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut output: Box<State> = <_>::default();
+
+        for wrapping in output.rng.iter_mut() {
+            wrapping.0 = reader.u32()?;
+        }
+
+        output.budget = Budget {
+            last_instant: reader.varint()?,
+            splats_per_instant: reader.varint()?,
+        };
+
+        output.current = reader.varint()?;
+        let (x, y) = reader.xy()?;
+        output.player = Player { x, y };
+
+        output.last_outcome = match reader.u8()? {
+            tag::SUCCESS => AdvanceOutcome::Success,
+            tag::OUT_OF_INSTANTS => AdvanceOutcome::OutOfInstants,
+            tag::OUT_OF_SPLATS => AdvanceOutcome::OutOfSplats,
+            _ => return Err(reader.invalid()),
+        };
+
+        output.time_mode = reader.time_mode()?;
+
+        output.rewind = match reader.u8()? {
+            tag::NONE => None,
+            tag::SOME => {
+                let action = match reader.u8()? {
+                    tag::WAIT => Action::Wait,
+                    tag::MOVE => Action::Move(match reader.u8()? {
+                        tag::UP => Dir::Up,
+                        tag::DOWN => Dir::Down,
+                        tag::LEFT => Dir::Left,
+                        tag::RIGHT => Dir::Right,
+                        tag::UP_LEFT => Dir::UpLeft,
+                        tag::UP_RIGHT => Dir::UpRight,
+                        tag::DOWN_LEFT => Dir::DownLeft,
+                        tag::DOWN_RIGHT => Dir::DownRight,
+                        _ => return Err(reader.invalid()),
+                    }),
+                    tag::JUMP => Action::Jump {
+                        from: reader.varint()?,
+                        to: reader.varint()?,
+                    },
+                    _ => return Err(reader.invalid()),
+                };
+                let (x, y) = reader.xy()?;
+
+                Some(Rewind {
+                    action,
+                    player: Player { x, y },
+                    current: reader.varint()?,
+                    time_mode: reader.time_mode()?,
+                    self_id: reader.varint()?,
+                })
+            },
+            _ => return Err(reader.invalid()),
+        };
+
+        output.instants = reader.timeline()?;
+
+        output.rules = match reader.u8()? {
+            tag::PARADOX => Rules::Paradox,
+            tag::BRANCHING => Rules::Branching,
+            tag::SELF_CONSISTENT => Rules::SelfConsistent,
+            _ => return Err(reader.invalid()),
+        };
+        output.self_id = reader.varint()?;
+        output.last_self_id = reader.varint()?;
+
+        let active = reader.varint()?;
+        let branch_count = reader.varint()?;
+        if active >= branch_count {
+            return Err(reader.invalid());
+        }
+        let mut list = Vec::new();
+        for _ in 0..branch_count {
+            let parent = match reader.u8()? {
+                tag::NONE => None,
+                tag::SOME => Some(reader.varint()?),
+                _ => return Err(reader.invalid()),
+            };
+            if parent.is_some_and(|parent| parent >= branch_count) {
+                return Err(reader.invalid());
+            }
+            let forked_at = reader.varint()?;
+            let instants = reader.timeline()?;
+            let current = reader.varint()?;
+            let (x, y) = reader.xy()?;
+            let self_id = reader.varint()?;
+
+            list.push(Branch {
+                parent,
+                forked_at,
+                instants,
+                current,
+                player: Player { x, y },
+                self_id,
+            });
+        }
+        output.branches = Branches { list, active };
+
+        let bookmark_count = reader.varint()?;
+        for _ in 0..bookmark_count {
+            let instant = reader.varint()?;
+            if output.bookmarks.last().is_some_and(|previous| instant < previous.instant) {
+                return Err(reader.invalid());
+            }
+
+            let len = reader.varint()? as usize;
+            if len > BOOKMARK_NAME_MAX_LEN {
+                return Err(reader.invalid());
+            }
+            let mut name = Vec::with_capacity(len);
+            for _ in 0..len {
+                name.push(reader.u8()?);
+            }
+            let name = String::from_utf8(name).map_err(|_| reader.invalid())?;
+
+            output.bookmarks.push(Bookmark { instant, name });
+        }
+
+        output.terrain = reader.terrain()?;
+
+        output.level = match reader.u8()? {
+            tag::NONE => None,
+            tag::SOME => Some(reader.varint()?),
+            _ => return Err(reader.invalid()),
+        };
+
+        if reader.offset != bytes.len() {
+            return Err(Error::TrailingBytes { offset: reader.offset });
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod to_bytes_then_from_bytes_is_identity {
+    use super::*;
+
+    #[test]
+    fn on_the_default_state() {
+        let expected = State::default();
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_with_a_time_jump() {
+        let mut expected = State::new([7; 16]);
+        expected.time_mode = TimeMode::Flowing;
+        for _ in 0..200 {
+            expected.move_right();
+            expected.advance_time();
+        }
+        expected.current = 100;
+        expected.move_down();
+        for _ in 0..10 {
+            expected.advance_time();
+        }
+        expected.time_mode = TimeMode::Manipulating(expected.fresh_time_input());
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_in_a_paradox() {
+        let mut expected = State::new([7; 16]);
+        expected.time_mode = TimeMode::Flowing;
+        for _ in 0..10 {
+            expected.step(None);
+        }
+        expected.time_mode = TimeMode::Manipulating(expected.fresh_time_input());
+        expected.jump_to(5);
+        assert!(expected.rewind.is_some());
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_with_branches() {
+        let mut expected = State::new([7; 16]);
+        expected.rules = Rules::Branching;
+        expected.time_mode = TimeMode::Flowing;
+        for _ in 0..10 {
+            expected.step(Some(Dir::Right));
+        }
+        expected.jump_to(5);
+        expected.step(Some(Dir::Down));
+        expected.jump_to(2);
+        expected.switch_branch(1);
+        expected.time_mode = TimeMode::ChoosingBranch(2);
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_with_bookmarks() {
+        let mut expected = State::new([7; 16]);
+        expected.time_mode = TimeMode::Flowing;
+        for _ in 0..10 {
+            expected.step(Some(Dir::Left));
+        }
+        expected.add_bookmark();
+        expected.current = 3;
+        let index = expected.add_bookmark();
+        expected.bookmarks[index as usize].name = "start of loop".to_string();
+        expected.time_mode = TimeMode::ChoosingBookmark(expected.fresh_time_input(), 1);
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+        assert_eq!(actual.bookmarks[0].instant, 3);
+    }
+
+    #[test]
+    fn on_this_state_with_walls() {
+        let mut expected = State::new([7; 16]);
+        expected.terrain = Terrain::parse("
+###.
+  #  #
+").unwrap();
+        // A wall in the bottom right corner, so the last run is not floor.
+        expected.terrain.set(xy::x(xy::MAX_W_INNER), xy::y(xy::MAX_H_INNER), Cell::Wall);
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_partway_through_a_level() {
+        let mut expected = State::new([7; 16]);
+        expected.start_level(2);
+        for _ in 0..3 {
+            expected.step(Some(Dir::Up));
+        }
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn on_this_state_viewing_spacetime() {
+        let mut expected = State::new([7; 16]);
+        expected.time_mode = TimeMode::Flowing;
+        for _ in 0..10 {
+            expected.step(Some(Dir::Up));
+        }
+        expected.time_mode = TimeMode::Spacetime(SpacetimeView {
+            axis: Axis::Y,
+            centre: 300,
+        });
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(*expected, *actual);
+    }
+
+    #[test]
+    fn with_a_budget_past_the_defaults() {
+        let mut expected = State {
+            budget: Budget {
+                last_instant: InstantIndex::MAX,
+                splats_per_instant: 100_000,
+            },
+            current: 1_000_000,
+            ..<_>::default()
+        };
+        expected.instants.push(999_999, Splat::default());
+
+        let actual = State::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(expected, *actual);
+    }
+}
+
+#[cfg(test)]
+mod rewind_returns_to_the_state_before {
+    use super::*;
+
+    fn flowing_at(x: xy::Inner, y: xy::Inner) -> State {
+        State {
+            player: Player { x: xy::x(x), y: xy::y(y) },
+            time_mode: TimeMode::Flowing,
+            ..<_>::default()
+        }
+    }
+
+    #[test]
+    fn a_jump_into_a_past_self() {
+        let mut state = flowing_at(0, 0);
+        for _ in 0..5 {
+            state.step(None);
+        }
+        state.time_mode = TimeMode::Manipulating(state.fresh_time_input());
+        let expected = state.clone();
+
+        state.jump_to(2);
+        assert_eq!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError {
+                instant: 2,
+                x: xy::x(0),
+                y: xy::y(0),
+                splat_index: 0,
+                kind: ParadoxKind::SameCell,
+            })
+        );
+        assert!(matches!(
+            state.rewind,
+            Some(Rewind { action: Action::Jump { from: 5, to: 2 }, .. })
+        ));
+
+        state.rewind();
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn a_move_into_a_past_self() {
+        let mut state = flowing_at(0, 0);
+        for _ in 0..3 {
+            state.step(None);
+        }
+        state.move_right();
+        state.jump_to(0);
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+        let expected = state.clone();
+
+        state.step(Some(Dir::Left));
+        assert!(matches!(state.time_mode, TimeMode::Collision(_)));
+
+        state.rewind();
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn a_move_into_the_second_of_two_past_selves() {
+        let mut state = flowing_at(0, 0);
+        state.step(None);
+        state.move_down();
+        state.move_right();
+        state.jump_to(0);
+        state.step(None);
+        state.move_up();
+        state.jump_to(0);
+        let expected = state.clone();
+
+        state.step(Some(Dir::Down));
+        assert_eq!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError {
+                instant: 0,
+                x: xy::x(1),
+                y: xy::y(1),
+                splat_index: 1,
+                kind: ParadoxKind::SameCell,
+            })
+        );
+
+        state.rewind();
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn waiting_for_a_past_self() {
+        let mut state = flowing_at(1, 0);
+        state.step(None);
+        state.step(Some(Dir::Left));
+        state.jump_to(0);
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+        let expected = state.clone();
+
+        state.step(None);
+        state.step(None);
+        assert!(matches!(
+            state.rewind,
+            Some(Rewind { action: Action::Wait, .. })
+        ));
+
+        state.rewind();
+
+        assert_eq!(state, expected);
+    }
+}
+
+#[cfg(test)]
+mod undoing {
+    use super::*;
+
+    fn flowing(rules: Rules) -> State {
+        State {
+            time_mode: TimeMode::Flowing,
+            rules,
+            ..<_>::default()
+        }
+    }
+
+    #[test]
+    fn a_move_takes_back_the_waits_after_it_and_redoing_puts_them_back() {
+        let mut state = flowing(Rules::Paradox);
+        state.step_undoably(None);
+        state.step_undoably(None);
+        let before = state.clone();
+
+        state.step_undoably(Some(Dir::Right));
+        state.step_undoably(None);
+        state.step_undoably(None);
+        let after = state.clone();
+
+        state.undo();
+        assert!(state.history.can_redo());
+        assert_eq!(
+            (&state.instants, state.player, state.current),
+            (&before.instants, before.player, before.current)
+        );
+
+        // Time keeps flowing between the undo and the redo.
+        state.step_undoably(None);
+        state.redo();
+
+        assert_eq!(state, after);
+    }
+
+    #[test]
+    fn a_jump_throws_away_the_branch_it_forked() {
+        let mut state = flowing(Rules::Branching);
+        for _ in 0..4 {
+            state.step_undoably(None);
+        }
+        let before = state.clone();
+
+        state.jump_to_undoably(1);
+        state.step_undoably(Some(Dir::Down));
+        assert_eq!(state.branches.count(), 2);
+
+        state.undo();
+        state.undo();
+
+        assert!(!state.history.can_undo());
+        assert_eq!(state.branches.count(), 1);
+        assert_eq!(
+            (&state.instants, state.player, state.current, state.self_id, state.last_self_id),
+            (&before.instants, before.player, before.current, before.self_id, before.last_self_id)
+        );
+    }
+}
+
+#[cfg(test)]
+mod jumping_back_with_branching_rules {
+    use super::*;
+
+    fn branching_after_steps(count: InstantIndex) -> State {
+        let mut state = State {
+            rules: Rules::Branching,
+            time_mode: TimeMode::Flowing,
+            ..<_>::default()
+        };
+        for _ in 0..count {
+            state.step(Some(Dir::Right));
+        }
+        state
+    }
+
+    #[test]
+    fn leaves_the_original_history_intact() {
+        let mut state = branching_after_steps(10);
+        let original = state.instants.clone();
+
+        state.jump_to(3);
+        for _ in 0..10 {
+            state.step(None);
+        }
+
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+        assert_eq!(state.branches.count(), 2);
+        assert_eq!(state.branches.active(), 1);
+
+        state.switch_branch(0);
+
+        assert_eq!(state.instants, original);
+        assert_eq!(state.current, 10);
+    }
+
+    #[test]
+    fn into_a_paradox_leaves_no_branch_behind() {
+        let mut state = State {
+            rules: Rules::Branching,
+            ..<_>::default()
+        };
+        state.start_level(1);
+        let (x, y, _) = state.terrain.iter()
+            .find(|&(_, _, cell)| cell == Cell::Door(0))
+            .unwrap();
+        state.player = Player { x, y };
+        state.current = 5;
+
+        state.jump_to(2);
+        assert!(matches!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError { kind: ParadoxKind::ShutIn, .. })
+        ));
+        assert_eq!(state.branches.count(), 1);
+
+        state.rewind();
+        assert_eq!(state.branches.count(), 1);
+        assert_eq!((state.current, state.self_id, state.last_self_id), (5, 0, 0));
+    }
+
+    #[test]
+    fn then_switching_back_resumes_the_new_branch() {
+        let mut state = branching_after_steps(10);
+
+        state.jump_to(3);
+        state.step(Some(Dir::Down));
+        let expected = (state.instants.clone(), state.current, state.player);
+
+        state.switch_branch(0);
+        state.switch_branch(1);
+
+        assert_eq!((state.instants.clone(), state.current, state.player), expected);
+        assert_eq!(state.branches.get(1).map(|b| (b.parent, b.forked_at)), Some((Some(0), 3)));
+    }
+}
+
+#[cfg(test)]
+mod each_jump_starts_a_new_self {
+    use super::*;
+
+    #[test]
+    fn which_can_be_found_afterwards() {
+        let mut state = State {
+            time_mode: TimeMode::Flowing,
+            ..<_>::default()
+        };
+        for _ in 0..3 {
+            state.step(Some(Dir::Right));
+        }
+        state.jump_to(0);
+        state.step(Some(Dir::Down));
+        state.jump_to(0);
+        state.step(Some(Dir::Down));
+
+        assert_eq!(state.self_id, 2);
+        assert_eq!(state.self_count_at(0), 3);
+        assert_eq!(state.self_count_at(1), 1);
+        assert_eq!(state.self_position(0, 1), Some((xy::x(2), xy::y(0))));
+        assert_eq!(state.self_position(1, 0), Some((xy::x(3), xy::y(1))));
+        assert_eq!(state.self_position(2, 0), Some((xy::x(3), xy::y(2))));
+        assert_eq!(state.self_position(1, 1), None);
+    }
+}
+
+#[cfg(test)]
+mod passing_through_a_past_self {
+    use super::*;
+
+    /// Sets up a past self that goes from (1, 0) to (0, 0), and a current
+    /// self that is at (0, 0) for instant 0, about to move right.
+    fn about_to_pass_through(rules: Rules) -> State {
+        let mut state = State {
+            player: Player { x: xy::x(1), y: xy::y(0) },
+            time_mode: TimeMode::Flowing,
+            rules,
+            ..<_>::default()
+        };
+        state.step(None);
+        state.step(Some(Dir::Left));
+        state.jump_to(0);
+        state.step(None);
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+
+        state
+    }
+
+    #[test]
+    fn is_a_paradox_under_self_consistent_rules() {
+        let mut state = about_to_pass_through(Rules::SelfConsistent);
+        let expected = state.clone();
+
+        state.step(Some(Dir::Right));
+        assert_eq!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError {
+                instant: 1,
+                x: xy::x(1),
+                y: xy::y(0),
+                splat_index: 0,
+                kind: ParadoxKind::Crossed,
+            })
+        );
+
+        state.rewind();
+
+        assert_eq!(state, expected);
+    }
+
+    #[test]
+    fn is_fine_under_paradox_rules() {
+        let mut state = about_to_pass_through(Rules::Paradox);
+
+        state.step(Some(Dir::Right));
+
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+    }
+
+    /// Sets up a past self that waits at (1, 0), then moves diagonally down
+    /// and left to (0, 1), for instant 2, and a current self at (0, 0) for
+    /// instant 1, about to make a move of their own.
+    fn beside_a_diagonal_move(terrain: Terrain) -> State {
+        let mut state = State {
+            player: Player { x: xy::x(1), y: xy::y(0) },
+            time_mode: TimeMode::Flowing,
+            rules: Rules::SelfConsistent,
+            terrain,
+            ..<_>::default()
+        };
+        state.step(None);
+        state.step(None);
+        state.step(Some(Dir::DownLeft));
+        assert_eq!(state.player, Player { x: xy::x(0), y: xy::y(1) });
+        state.jump_to(0);
+        state.step(None);
+        state.step(Some(Dir::Up));
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+
+        state
+    }
+
+    #[test]
+    fn diagonally_is_a_paradox_under_self_consistent_rules() {
+        let mut state = beside_a_diagonal_move(<_>::default());
+
+        state.step(Some(Dir::DownRight));
+        assert_eq!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError {
+                instant: 2,
+                x: xy::x(1),
+                y: xy::y(1),
+                splat_index: 0,
+                kind: ParadoxKind::Crossed,
+            })
+        );
+    }
+
+    #[test]
+    fn is_a_paradox_when_squeezing_past_diagonally_too() {
+        let mut state = beside_a_diagonal_move(Terrain::parse("..\n.#").unwrap());
+
+        state.step(None);
+        assert_eq!(
+            state.time_mode,
+            TimeMode::Collision(CollisionError {
+                instant: 2,
+                x: xy::x(0),
+                y: xy::y(0),
+                splat_index: 0,
+                kind: ParadoxKind::Blocked,
+            })
+        );
+    }
+
+    #[test]
+    fn does_not_include_standing_beside_a_diagonal_move_with_room() {
+        let mut state = beside_a_diagonal_move(<_>::default());
+
+        state.step(None);
+
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+    }
+}
+
+#[cfg(test)]
+mod walls {
+    use super::*;
+
+    #[test]
+    fn stop_the_player_but_still_let_time_pass() {
+        let mut state = State {
+            player: Player { x: xy::x(1), y: xy::y(1) },
+            time_mode: TimeMode::Flowing,
+            terrain: Terrain::parse("\n#.").unwrap(),
+            ..<_>::default()
+        };
+
+        state.step(Some(Dir::Left));
+        assert_eq!((state.player.x, state.player.y), (xy::x(1), xy::y(1)));
+        assert_eq!(state.current, 1);
+
+        state.step(Some(Dir::Up));
+        assert_eq!((state.player.x, state.player.y), (xy::x(1), xy::y(0)));
+    }
+
+    #[test]
+    fn only_let_the_player_past_a_corner_diagonally_if_there_is_room() {
+        let mut state = State {
+            player: Player { x: xy::x(1), y: xy::y(1) },
+            time_mode: TimeMode::Flowing,
+            terrain: Terrain::parse(".#\n#.\n..#").unwrap(),
+            ..<_>::default()
+        };
+
+        // Squeezing between the two walls is not allowed, and there is
+        // nowhere to slide to.
+        state.step(Some(Dir::UpLeft));
+        assert_eq!((state.player.x, state.player.y), (xy::x(1), xy::y(1)));
+
+        state.step(Some(Dir::DownLeft));
+        assert_eq!((state.player.x, state.player.y), (xy::x(0), xy::y(2)));
+
+        state.step(Some(Dir::UpRight));
+        assert_eq!((state.player.x, state.player.y), (xy::x(1), xy::y(1)));
+
+        // The way down and right is blocked, so the player slides right.
+        state.step(Some(Dir::DownRight));
+        assert_eq!((state.player.x, state.player.y), (xy::x(2), xy::y(1)));
+    }
+
+    #[test]
+    fn that_are_misdrawn_give_the_line_and_column() {
+        assert_eq!(
+            Terrain::parse("#.\n.x"),
+            Err(TerrainError::UnknownCell { line: 2, column: 2, character: 'x' })
+        );
+    }
+}
+
+#[cfg(test)]
+mod level_files {
+    use super::*;
+
+    #[test]
+    fn that_are_embedded_are_valid_and_survive_being_written_out() {
+        for text in levels::EMBEDDED {
+            let level = level_file::parse(text).unwrap();
+
+            assert_eq!(level_file::parse(&level_file::write(&level)), Ok(level));
+        }
+    }
+
+    #[test]
+    fn with_awkward_names_survive_being_written_out() {
+        for name in [" spaced out ", "two\nlines", "\"quoted\"", "back\\slash\t\u{7f}", "", "a = b"] {
+            let level = Level {
+                name: name.to_string(),
+                ..levels::all()[0].clone()
+            };
+
+            assert_eq!(level_file::parse(&level_file::write(&level)), Ok(level), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn with_mistakes_give_the_line_and_column() {
+        let text = "\
+[level]
+name = oops
+start = 1, 2
+
+[objects]
+door x = 3, 4
+";
+
+        assert_eq!(
+            level_file::parse(text),
+            Err(level_file::Error {
+                line: 6,
+                column: 6,
+                kind: level_file::ErrorKind::InvalidNumber("x".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn that_start_after_the_last_instant_give_the_line_and_column() {
+        let text = "\
+[level]
+name = too late
+start = 1, 2
+last instant = 10
+instant = 11
+";
+
+        assert_eq!(
+            level_file::parse(text),
+            Err(level_file::Error {
+                line: 5,
+                column: 11,
+                kind: level_file::ErrorKind::StartsPastLastInstant,
+            })
+        );
+    }
+
+    #[test]
+    fn with_a_badly_quoted_name_give_the_line_and_column() {
+        let text = "\
+[level]
+name = \"not \\q\"
+start = 1, 2
+";
+
+        assert_eq!(
+            level_file::parse(text),
+            Err(level_file::Error {
+                line: 2,
+                column: 8,
+                kind: level_file::ErrorKind::InvalidName,
+            })
+        );
+    }
+
+    #[test]
+    fn with_mistakes_in_the_grid_give_the_line_and_column_in_the_file() {
+        let text = "\
+[level]
+name = oops
+start = 0, 0
+
+[grid]
+.....
+..#?#
+";
+
+        assert_eq!(
+            level_file::parse(text),
+            Err(level_file::Error {
+                line: 7,
+                column: 4,
+                kind: level_file::ErrorKind::UnknownCell('?'),
+            })
+        );
+    }
+}
+
+#[cfg(test)]
+mod doors {
+    use super::*;
+
+    fn on_level(index: LevelIndex) -> State {
+        let mut state = State::default();
+        state.start_level(index);
+        state
+    }
+
+    #[test]
+    fn stay_shut_without_someone_on_a_plate() {
+        let mut state = on_level(1);
+        let door = levels::all()[1].terrain.iter()
+            .find(|&(_, _, cell)| cell == Cell::Door(0))
+            .map(|(x, y, _)| (x, y))
+            .unwrap();
+        let mut beside = door.0;
+        beside -= X::ONE;
+        state.player = Player { x: beside, y: door.1 };
+
+        state.step(Some(Dir::Right));
+
+        assert_eq!((state.player.x, state.player.y), (beside, door.1));
+    }
+
+    #[test]
+    fn let_the_player_through_while_a_past_self_holds_one_open() {
+        let mut state = on_level(1);
+
+        for dir in [Dir::Up, Dir::Up, Dir::Up, Dir::Up, Dir::Right] {
+            state.step(Some(dir));
+        }
+        for _ in 0..20 {
+            state.step(None);
+        }
+        state.jump_to(0);
+        assert_eq!(state.time_mode, TimeMode::Flowing);
+
+        for _ in 0..6 {
+            state.step(Some(Dir::Right));
+        }
+        state.step(Some(Dir::Down));
+        state.step(Some(Dir::Down));
+        for _ in 0..8 {
+            assert_eq!(state.time_mode, TimeMode::Flowing);
+            state.step(Some(Dir::Right));
+        }
+
+        assert_eq!(state.time_mode, TimeMode::Won);
+    }
+}
+
+#[cfg(test)]
+mod typing_in_a_time {
+    use super::*;
+
+    #[test]
+    fn replaces_the_value_then_adds_digits_up_to_the_max() {
+        let state = State {
+            current: 12,
+            budget: Budget {
+                last_instant: 5000,
+                ..<_>::default()
+            },
+            ..<_>::default()
+        };
+        let mut input = state.fresh_time_input();
+
+        for digit in [4, 0, 0, 0] {
+            input.type_digit(digit);
+        }
+        assert_eq!(input.get_value(), 4000);
+
+        input.type_digit(7);
+        assert_eq!(input.get_value(), 5000);
+
+        input.backspace();
+        assert_eq!(input.get_value(), 500);
+
+        input.saturating_add(1);
+        input.type_digit(3);
+        assert_eq!(input.get_value(), 3);
+    }
+}
+
+#[cfg(test)]
+mod from_bytes_rejects {
+    use super::*;
+
+    #[test]
+    fn the_empty_slice() {
+        assert_eq!(State::from_bytes(&[]).err(), Some(save::Error::NotASave));
+    }
+
+    #[test]
+    fn bytes_with_the_wrong_magic() {
+        assert_eq!(
+            State::from_bytes(b"PNG\0\x01\x00").err(),
+            Some(save::Error::NotASave)
+        );
+    }
+
+    #[test]
+    fn saves_from_other_versions() {
+        let mut bytes = save::MAGIC.to_vec();
+        bytes.extend_from_slice(&(save::VERSION + 1).to_le_bytes());
+
+        assert_eq!(
+            State::from_bytes(&bytes).err(),
+            Some(save::Error::UnsupportedVersion(save::VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn saves_with_out_of_order_instants() {
+        let with_second_instant_at = |second| {
+            let mut state = State::default();
+            state.instants.push(3, Splat::default());
+            state.instants.push(second, Splat::default());
+            state.to_bytes()
+        };
+
+        // The two saves differ only in the gap from the first instant to the
+        // second, so the one byte where they differ is where that gap is,
+        // however the rest of the format changes.
+        let mut bytes = with_second_instant_at(5);
+        let other = with_second_instant_at(6);
+        assert_eq!(bytes.len(), other.len());
+        let differing = (0..bytes.len())
+            .filter(|&i| bytes[i] != other[i])
+            .collect::<Vec<_>>();
+        assert_eq!(differing.len(), 1);
+        let gap_index = differing[0];
+        assert_eq!(bytes[gap_index], 2);
+        bytes[gap_index] = 0;
+
+        assert_eq!(
+            State::from_bytes(&bytes).err(),
+            Some(save::Error::InvalidValue { offset: gap_index })
+        );
+    }
+
+    #[test]
+    fn saves_that_end_early() {
+        let mut bytes = save::MAGIC.to_vec();
+        bytes.extend_from_slice(&save::VERSION.to_le_bytes());
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        assert_eq!(
+            State::from_bytes(&bytes).err(),
+            Some(save::Error::UnexpectedEnd)
+        );
+    }
+}
+
+#[cfg(test)]
+mod ghosts {
+    use super::*;
+
+    #[test]
+    fn trail_back_along_their_own_worldline_only() {
+        let mut state = State {
+            time_mode: TimeMode::Flowing,
+            ..<_>::default()
+        };
+        state.step(Some(Dir::Right));
+        state.step(Some(Dir::Right));
+        state.step(Some(Dir::Down));
+        state.jump_to(1);
+        state.step(Some(Dir::Down));
+        state.step(Some(Dir::Down));
+
+        let ghosts = state.ghosts(2, GHOST_TRAIL_LEN);
+        assert_eq!(ghosts.len(), 2);
+
+        // The first self has been around since instant 0.
+        assert_eq!(ghosts[0].self_id, 0);
+        assert_eq!(ghosts[0].trail.len(), 2);
+        assert_eq!(ghosts[0].trail[0], state.self_position(0, 1).unwrap());
+
+        // The second self only showed up at instant 1.
+        assert_eq!(ghosts[1].self_id, 1);
+        assert_eq!(ghosts[1].colour, self_colour(1));
+        assert_eq!(ghosts[1].trail, [state.self_position(1, 1).unwrap()]);
+    }
+
+    #[test]
+    fn glide_to_the_shown_instant_instead_of_jumping_there() {
+        let mut state = State {
+            time_mode: TimeMode::Flowing,
+            current: 100,
+            ..<_>::default()
+        };
+
+        let mut frames = 0;
+        while state.ghost_view.instant != state.shown_instant() {
+            let before = state.ghost_view.instant;
+            state.ease_ghosts();
+            assert!(state.ghost_view.instant > before);
+            frames += 1;
+        }
+
+        assert!(frames > 1, "{frames}");
+        assert!(frames < 30, "{frames}");
     }
 }
@@ -13,12 +13,37 @@ use winit::{
 
 use render::{clip, FrameBuffer, NeedsRedraw};
 
-pub fn run<S: State + 'static>(mut state: S) {
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// If set, the window starts out this many times the size of the
+    /// `command` screen. Otherwise the size is left up to the windowing
+    /// system. Ignored on the web, where the page decides the size.
+    pub scale: Option<u16>,
+}
+
+pub fn run<S: State + 'static>(state: S) {
+    run_with_options(state, Options::default())
+}
+
+pub fn run_with_options<S: State + 'static>(mut state: S, options: Options) {
     let event_loop = EventLoop::new();
 
     let builder = WindowBuilder::new()
         .with_title("time-travel-sim");
 
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = match options.scale {
+        Some(scale) => builder.with_inner_size(
+            winit::dpi::PhysicalSize::new(
+                u32::from(platform_types::command::WIDTH) * u32::from(scale),
+                u32::from(platform_types::command::HEIGHT) * u32::from(scale),
+            )
+        ),
+        None => builder,
+    };
+    #[cfg(target_arch = "wasm32")]
+    let _ = options;
+
     #[cfg(target_arch = "wasm32")]
     let builder = wasm::set_canvas(builder);
 
@@ -68,6 +93,18 @@ pub fn run<S: State + 'static>(mut state: S) {
                 use winit::event::{ElementState, VirtualKeyCode as VK};
                 use platform_types::Button;
 
+                match (keycode, element_state) {
+                    (VK::F5, ElementState::Pressed) => {
+                        state.quick_save();
+                        return
+                    },
+                    (VK::F9, ElementState::Pressed) => {
+                        state.quick_load();
+                        return
+                    },
+                    _ => {}
+                }
+
                 let button = match keycode {
                     VK::Return => Button::START,
                     VK::RShift => Button::SELECT,
@@ -91,6 +128,19 @@ pub fn run<S: State + 'static>(mut state: S) {
                     ElementState::Released => state.release(button),
                 }
             }
+            Event::WindowEvent {
+                event: WindowEvent::ReceivedCharacter(character),
+                window_id,
+            } if window_id == window.id() => {
+                use platform_types::Typed;
+
+                match character {
+                    '\u{8}' => state.typed(Typed::Backspace),
+                    // Enter and the like already come through as presses.
+                    _ if character.is_control() => {},
+                    _ => state.typed(Typed::Char(character)),
+                }
+            }
             Event::WindowEvent {
                 event: WindowEvent::Focused(true),
                 window_id,
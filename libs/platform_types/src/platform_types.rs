@@ -1064,6 +1064,14 @@ pub mod button {
         pub fn remove(&mut self, other: Self) {
             self.0 &= !other.0;
         }
+
+        pub const fn bits(self) -> u8 {
+            self.0
+        }
+
+        pub const fn from_bits(bits: u8) -> Self {
+            Self(bits)
+        }
     }
 }
 pub use button::Button;
@@ -1072,12 +1080,32 @@ pub type Logger = Option<fn(&str) -> ()>;
 
 pub type StateParams = ([u8; 16], Logger, Logger);
 
+/// Something typed on a keyboard, for entering text, as opposed to pressing
+/// the `Button`s a key may also be mapped to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Typed {
+    Char(char),
+    Backspace,
+}
+
 pub trait State {
     fn frame(&mut self) -> (&[Command], &[SFX]);
 
     fn press(&mut self, button: Button);
 
     fn release(&mut self, button: Button);
+
+    /// Called for each character typed, in addition to any `press` calls
+    /// for the same keys. Does nothing by default.
+    fn typed(&mut self, _typed: Typed) {}
+
+    /// Save the current state somewhere it can be loaded from later, if the
+    /// platform supports that. Does nothing by default.
+    fn quick_save(&mut self) {}
+
+    /// Load whatever `quick_save` last saved, if the platform supports that.
+    /// Does nothing by default.
+    fn quick_load(&mut self) {}
 }
 
 // reportedly colourblind friendly colours
@@ -219,6 +219,7 @@ pub enum NeedsRedraw {
     target_arch = "x86_64",
     target_feature = "sse",
     target_feature = "sse2",
+    not(feature = "scalar-wide"),
 ))]
 mod wide {
     use super::*;
@@ -511,7 +512,13 @@ mod wide {
             $a: expr $(,)?
         ) => ({#[allow(unused_unsafe)]
             unsafe {
-                core::arch::x86_64::_mm_rcp_ps($a)
+                // `_mm_rcp_ps` would be quicker, but it is only an
+                // approximation, which differs between CPUs, and from the
+                // other versions, which all divide.
+                core::arch::x86_64::_mm_div_ps(
+                    core::arch::x86_64::_mm_set_ps1(1.),
+                    $a
+                )
             }
         });
     }
@@ -572,6 +579,7 @@ mod wide {
 #[cfg(all(
     target_arch = "wasm32",
     target_feature = "simd128",
+    not(feature = "scalar-wide"),
 ))]
 mod wide {
     use super::*;
@@ -862,9 +870,6 @@ mod wide {
             $a: expr $(,)?
         ) => ({#[allow(unused_unsafe)]
             unsafe {
-                // As of this writing, there's no equivalent wasm instrinsic to
-                // `_mm_rcp_ps`, so it seems we need to eat the extra divide
-                // latency. :(
                 core::arch::wasm32::f32x4_div(
                     core::arch::wasm32::f32x4_splat(1.),
                     $a
@@ -926,22 +931,336 @@ mod wide {
     pub use _gt_mask_32 as gt_mask_32;
 }
 
-#[cfg(not(any(
-    all(
-        target_arch = "x86_64",
-        target_feature = "sse",
-        target_feature = "sse2",
-    ),
-    all(
-        target_arch = "wasm32",
-        target_feature = "simd128",
-    ),
-)))]
+// A plain Rust version, for targets without one of the SIMD versions above,
+// or when the `scalar-wide` feature is on. Each lane is worked on one at a
+// time, matching what the x86_64 version does.
+#[cfg(any(
+    feature = "scalar-wide",
+    not(any(
+        all(
+            target_arch = "x86_64",
+            target_feature = "sse",
+            target_feature = "sse2",
+        ),
+        all(
+            target_arch = "wasm32",
+            target_feature = "simd128",
+        ),
+    )),
+))]
 mod wide {
     use super::*;
     pub const WIDTH: unscaled::Inner = 4;
 
-    compile_error!("wide not implemented for this target");
+    pub type I32x4 = [i32; WIDTH as usize];
+    pub type F32x4 = [f32; WIDTH as usize];
+
+    pub fn zip_i32(a: I32x4, b: I32x4, f: impl Fn(i32, i32) -> i32) -> I32x4 {
+        core::array::from_fn(|i| f(a[i], b[i]))
+    }
+
+    pub fn zip_f32(a: F32x4, b: F32x4, f: impl Fn(f32, f32) -> f32) -> F32x4 {
+        core::array::from_fn(|i| f(a[i], b[i]))
+    }
+
+    pub fn mask(condition: bool) -> i32 {
+        if condition { -1 } else { 0 }
+    }
+
+    /// SAFETY: The pointers produced by the code generated by these macros must
+    /// be valid to read 128 bytes from.
+    /// We omit unsafe in the macro's code in order to encourage the user to notice
+    /// the invariants they are expected to uphold.
+    #[macro_export]
+    macro_rules! _load {
+        (
+            $addr: expr $(,)?
+        ) => ({
+            let addr: *const _ = $addr;
+            core::ptr::read_unaligned(
+                addr as *const $crate::wide::I32x4
+            )
+        });
+        (
+            $addr: expr,
+            $index: expr $(,)?
+        ) => ({
+            let addr: *const _ = $addr;
+            core::ptr::read_unaligned(
+                addr.add($index) as *const $crate::wide::I32x4
+            )
+        });
+    }
+    pub use _load as load;
+
+    /// SAFETY: The pointers produced by the code generated by these macros must
+    /// be valid to write 128 bytes to.
+    /// We omit unsafe in the macro's code in order to encourage the user to notice
+    /// the invariants they are expected to uphold.
+    #[macro_export]
+    macro_rules! _store {
+        (
+            $vector: expr,
+            $addr: expr $(,)?
+        ) => ({
+            let vector: $crate::wide::I32x4 = $vector;
+            let addr: *mut _ = $addr;
+            core::ptr::write_unaligned(
+                addr as *mut $crate::wide::I32x4,
+                vector,
+            )
+        });
+        (
+            $vector: expr,
+            $addr: expr,
+            $index: expr $(,)?
+        ) => ({
+            let vector: $crate::wide::I32x4 = $vector;
+            let addr: *mut _ = $addr;
+            core::ptr::write_unaligned(
+                addr.add($index) as *mut $crate::wide::I32x4,
+                vector,
+            )
+        });
+    }
+    pub use _store as store;
+
+    #[macro_export]
+    macro_rules! _pick_via_mask {
+        (
+            $zero_case: expr,
+            $one_case: expr,
+            $mask: expr $(,)?
+        ) => ({
+            let zero_case: $crate::wide::I32x4 = $zero_case;
+            let one_case: $crate::wide::I32x4 = $one_case;
+            let mask: $crate::wide::I32x4 = $mask;
+            core::array::from_fn::<i32, 4, _>(|i|
+                (mask[i] & one_case[i]) | (!mask[i] & zero_case[i])
+            )
+        });
+    }
+    pub use _pick_via_mask as pick_via_mask;
+
+    #[macro_export]
+    macro_rules! _i32x4 {
+        (
+            $e0: expr,
+            $e1: expr,
+            $e2: expr,
+            $e3: expr $(,)?
+        ) => ({
+            let output: $crate::wide::I32x4 = [$e0, $e1, $e2, $e3];
+            output
+        });
+    }
+    pub use _i32x4 as i32x4;
+
+    #[macro_export]
+    macro_rules! _and {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($a, $b, |a, b| a & b)
+        });
+    }
+    pub use _and as and;
+
+    #[macro_export]
+    macro_rules! _and_not {
+        (
+            $just_anded: expr,
+            $notted: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($just_anded, $notted, |a, b| a & !b)
+        });
+    }
+    pub use _and_not as and_not;
+
+    #[macro_export]
+    macro_rules! _or {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($a, $b, |a, b| a | b)
+        });
+    }
+    pub use _or as or;
+
+    // Shifting by 32 or more gives 0, as the SIMD versions do.
+    #[macro_export]
+    macro_rules! _left_shift_32 {
+        (
+            $a: expr,
+            $imm8: expr $(,)?
+        ) => ({
+            let a: $crate::wide::I32x4 = $a;
+            let imm8: u32 = $imm8;
+            a.map(|a| (a as u32).checked_shl(imm8).unwrap_or(0) as i32)
+        });
+    }
+    pub use _left_shift_32 as left_shift_32;
+
+    #[macro_export]
+    macro_rules! _right_shift_32 {
+        (
+            $a: expr,
+            $imm8: expr $(,)?
+        ) => ({
+            let a: $crate::wide::I32x4 = $a;
+            let imm8: u32 = $imm8;
+            a.map(|a| (a as u32).checked_shr(imm8).unwrap_or(0) as i32)
+        });
+    }
+    pub use _right_shift_32 as right_shift_32;
+
+    // Rounds to nearest, ties to even, like `_mm_cvtps_epi32` does by default.
+    #[macro_export]
+    macro_rules! _f32_to_u32 {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: $crate::wide::F32x4 = $a;
+            a.map(|a| a.round_ties_even() as i32)
+        });
+    }
+    pub use _f32_to_u32 as f32_to_u32;
+
+    #[macro_export]
+    macro_rules! _u32_to_f32 {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: $crate::wide::I32x4 = $a;
+            a.map(|a| a as f32)
+        });
+    }
+    pub use _u32_to_f32 as u32_to_f32;
+
+    #[macro_export]
+    macro_rules! _i32 {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: i32 = $a;
+            let output: $crate::wide::I32x4 = [a; 4];
+            output
+        });
+    }
+    pub use _i32 as i32;
+
+    #[macro_export]
+    macro_rules! _f32 {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: f32 = $a;
+            let output: $crate::wide::F32x4 = [a; 4];
+            output
+        });
+    }
+    pub use _f32 as f32;
+
+    #[macro_export]
+    macro_rules! _add_f32 {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_f32($a, $b, |a, b| a + b)
+        });
+    }
+    pub use _add_f32 as add_f32;
+
+    #[macro_export]
+    macro_rules! _add_i32 {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($a, $b, i32::wrapping_add)
+        });
+    }
+    pub use _add_i32 as add_i32;
+
+    #[macro_export]
+    macro_rules! _sub {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_f32($a, $b, |a, b| a - b)
+        });
+    }
+    pub use _sub as sub;
+
+    #[macro_export]
+    macro_rules! _mul {
+        (
+            $a: expr,
+            $b: expr $(,)?
+        ) => ({
+            $crate::wide::zip_f32($a, $b, |a, b| a * b)
+        });
+    }
+    pub use _mul as mul;
+
+    #[macro_export]
+    macro_rules! _recip {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: $crate::wide::F32x4 = $a;
+            a.map(|a| 1. / a)
+        });
+    }
+    pub use _recip as recip;
+
+    #[macro_export]
+    macro_rules! _sqrt {
+        (
+            $a: expr $(,)?
+        ) => ({
+            let a: $crate::wide::F32x4 = $a;
+            a.map(f32::sqrt)
+        });
+    }
+    pub use _sqrt as sqrt;
+
+    #[macro_export]
+    macro_rules! _lt_mask_32 {
+        (
+            $lhs: expr,
+            $rhs: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($lhs, $rhs, |a, b| $crate::wide::mask(a < b))
+        });
+    }
+    pub use _lt_mask_32 as lt_mask_32;
+
+    #[macro_export]
+    macro_rules! _eq_mask_u32 {
+        (
+            $lhs: expr,
+            $rhs: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($lhs, $rhs, |a, b| $crate::wide::mask(a == b))
+        });
+    }
+    pub use _eq_mask_u32 as eq_mask_u32;
+
+    #[macro_export]
+    macro_rules! _gt_mask_32 {
+        (
+            $lhs: expr,
+            $rhs: expr $(,)?
+        ) => ({
+            $crate::wide::zip_i32($lhs, $rhs, |a, b| $crate::wide::mask(a > b))
+        });
+    }
+    pub use _gt_mask_32 as gt_mask_32;
 }
 
 #[cfg(test)]
@@ -987,6 +1306,53 @@ mod wide_tests {
             to_array!(i32x4!(A, B, C, D))
         );
     }
+
+    #[test]
+    fn shifts_and_masks_return_the_expected_result_in_this_channel_splitting_case() {
+        let argb = i32x4!(F, A, 0x7F00_0000, 0x0012_3456);
+
+        let alpha = right_shift_32!(argb, 24);
+        let green = and!(right_shift_32!(argb, 8), i32!(255));
+        let is_opaque = eq_mask_u32!(alpha, i32!(255));
+        let is_less = lt_mask_32!(alpha, i32!(0x7F));
+
+        assert_eq!(to_array!(alpha), [0xFF, 0xAA, 0x7F, 0]);
+        assert_eq!(to_array!(green), [0xFF, 0xAA, 0, 0x34]);
+        assert_eq!(to_array!(is_opaque), [F, 0, 0, 0]);
+        assert_eq!(to_array!(is_less), [0, 0, 0, F]);
+        assert_eq!(to_array!(left_shift_32!(alpha, 24)), [F << 24, A << 24, 0x7F00_0000, 0]);
+        assert_eq!(to_array!(and_not!(argb, i32!(0x00FF_FFFF))), [F << 24, A << 24, 0x7F00_0000, 0]);
+    }
+
+    #[test]
+    fn f32_to_u32_rounds_half_to_even_in_this_case() {
+        let halves = mul!(
+            u32_to_f32!(i32x4!(1, 3, 5, 510)),
+            f32!(0.5)
+        );
+
+        let actual = f32_to_u32!(sqrt!(mul!(halves, halves)));
+
+        assert_eq!(to_array!(actual), [0, 2, 2, 255]);
+    }
+
+    #[test]
+    fn recip_divides_exactly_in_this_case() {
+        // Scaling by a power of two keeps every bit of the reciprocals, so
+        // an approximation would show up as being off by hundreds here.
+        const SCALE: f32 = (1 << 24) as f32;
+        let values = [1, 3, 7, 255];
+
+        let actual = f32_to_u32!(mul!(
+            recip!(u32_to_f32!(i32x4!(values[0], values[1], values[2], values[3]))),
+            f32!(SCALE)
+        ));
+
+        assert_eq!(
+            to_array!(actual),
+            values.map(|value| ((1. / value as f32) * SCALE).round_ties_even() as i32)
+        );
+    }
 }
 
 pub fn render(
@@ -0,0 +1,471 @@
+//! Recording and replaying the input fed to a `platform_types::State`.
+//!
+//! Given the same seed and the same starting point, the same input on the
+//! same frames produces the same session, so those are all a replay needs to
+//! hold.
+
+use platform_types::{Button, Command, State, Typed, SFX};
+use xs::Seed;
+
+use std::io::Write;
+
+pub const MAGIC: [u8; 4] = *b"TTSR";
+pub const VERSION: u16 = 3;
+
+/// Frames are counted from 0. An event on frame `n` happens just before the
+/// `n`th call to `frame`.
+pub type FrameIndex = u32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Press(Button),
+    Release(Button),
+    Typed(Typed),
+    QuickSave,
+    QuickLoad,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: Seed,
+    /// What the state needs, on top of the seed, to start out the same way
+    /// as when it was recorded, in a form of its own choosing. Empty if the
+    /// seed is all it needs.
+    pub start: Vec<u8>,
+    /// In the order they happened, so also in order of `FrameIndex`.
+    pub events: Vec<(FrameIndex, Event)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    NotAReplay,
+    UnsupportedVersion(u16),
+    UnexpectedEnd,
+    InvalidValue { offset: usize },
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use Error::*;
+        match self {
+            NotAReplay => write!(f, "not a time-travel-sim replay"),
+            UnsupportedVersion(version) => write!(
+                f,
+                "replay version {version} is not supported (expected {VERSION})"
+            ),
+            UnexpectedEnd => write!(f, "replay ended unexpectedly"),
+            InvalidValue { offset } => write!(f, "invalid value at byte {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+mod tag {
+    pub const PRESS: u8 = 0;
+    pub const RELEASE: u8 = 1;
+    pub const QUICK_SAVE: u8 = 2;
+    pub const QUICK_LOAD: u8 = 3;
+    pub const TYPED_CHAR: u8 = 4;
+    pub const TYPED_BACKSPACE: u8 = 5;
+}
+
+fn header(seed: Seed, start: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 2 + seed.len() + 5 + start.len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&seed);
+    push_varint(&mut bytes, start.len() as u32);
+    bytes.extend_from_slice(start);
+    bytes
+}
+
+/// Events are written one after the other with no count up front, so that a
+/// replay can be appended to as it is recorded.
+fn push_event(bytes: &mut Vec<u8>, frame_gap: FrameIndex, event: Event) {
+    push_varint(bytes, frame_gap);
+
+    match event {
+        Event::Press(button) => {
+            bytes.push(tag::PRESS);
+            bytes.push(button.bits());
+        },
+        Event::Release(button) => {
+            bytes.push(tag::RELEASE);
+            bytes.push(button.bits());
+        },
+        Event::Typed(Typed::Char(character)) => {
+            bytes.push(tag::TYPED_CHAR);
+            push_varint(bytes, character.into());
+        },
+        Event::Typed(Typed::Backspace) => bytes.push(tag::TYPED_BACKSPACE),
+        Event::QuickSave => bytes.push(tag::QUICK_SAVE),
+        Event::QuickLoad => bytes.push(tag::QUICK_LOAD),
+    }
+}
+
+fn push_varint(bytes: &mut Vec<u8>, mut n: u32) {
+    loop {
+        let byte = (n & 0x7F) as u8;
+        n >>= 7;
+        if n == 0 {
+            bytes.push(byte);
+            break
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+impl Replay {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = header(self.seed, &self.start);
+
+        let mut previous_frame = 0;
+        for &(frame, event) in &self.events {
+            push_event(&mut bytes, frame - previous_frame, event);
+            previous_frame = frame;
+        }
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        struct Reader<'bytes> {
+            bytes: &'bytes [u8],
+            offset: usize,
+        }
+
+        impl Reader<'_> {
+            fn u8(&mut self) -> Result<u8, Error> {
+                let byte = *self.bytes.get(self.offset).ok_or(Error::UnexpectedEnd)?;
+                self.offset += 1;
+                Ok(byte)
+            }
+
+            fn varint(&mut self) -> Result<u32, Error> {
+                let mut output: u32 = 0;
+                for shift in (0..32).step_by(7) {
+                    let byte = self.u8()?;
+                    let bits = u32::from(byte & 0x7F);
+                    if shift == 28 && bits > 0xF {
+                        return Err(self.invalid());
+                    }
+                    output |= bits << shift;
+
+                    if byte & 0x80 == 0 {
+                        return Ok(output)
+                    }
+                }
+
+                Err(self.invalid())
+            }
+
+            fn invalid(&self) -> Error {
+                Error::InvalidValue { offset: self.offset.saturating_sub(1) }
+            }
+        }
+
+        let mut reader = Reader { bytes, offset: 0 };
+
+        if bytes.get(..MAGIC.len()) != Some(&MAGIC[..]) {
+            return Err(Error::NotAReplay);
+        }
+        reader.offset = MAGIC.len();
+
+        let version = u16::from_le_bytes([reader.u8()?, reader.u8()?]);
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let mut output = Replay::default();
+        for byte in output.seed.iter_mut() {
+            *byte = reader.u8()?;
+        }
+
+        let start_len = reader.varint()? as usize;
+        let start = bytes.get(reader.offset..reader.offset.saturating_add(start_len))
+            .ok_or(Error::UnexpectedEnd)?;
+        output.start = start.to_vec();
+        reader.offset += start_len;
+
+        let mut frame: FrameIndex = 0;
+        while reader.offset < bytes.len() {
+            let gap = reader.varint()?;
+            frame = frame.checked_add(gap).ok_or_else(|| reader.invalid())?;
+
+            let event = match reader.u8()? {
+                tag::PRESS => Event::Press(Button::from_bits(reader.u8()?)),
+                tag::RELEASE => Event::Release(Button::from_bits(reader.u8()?)),
+                tag::TYPED_CHAR => {
+                    let character = char::from_u32(reader.varint()?)
+                        .ok_or_else(|| reader.invalid())?;
+                    Event::Typed(Typed::Char(character))
+                },
+                tag::TYPED_BACKSPACE => Event::Typed(Typed::Backspace),
+                tag::QUICK_SAVE => Event::QuickSave,
+                tag::QUICK_LOAD => Event::QuickLoad,
+                _ => return Err(reader.invalid()),
+            };
+
+            output.events.push((frame, event));
+        }
+
+        Ok(output)
+    }
+}
+
+/// Passes everything through to the wrapped state, while writing down the
+/// input as it goes. Each event is written as soon as it happens, so nothing
+/// is lost if the program exits without warning.
+pub struct Recorder<S, W> {
+    state: S,
+    // Set to `None` if writing fails, so we stop trying.
+    output: Option<W>,
+    frame: FrameIndex,
+    previous_event_frame: FrameIndex,
+}
+
+impl<S: State, W: Write> Recorder<S, W> {
+    /// `seed` should be the seed `state` was created with, and `start` what
+    /// else it needs to be set up as it is now, as in `Replay::start`.
+    pub fn new(state: S, seed: Seed, start: &[u8], mut output: W) -> std::io::Result<Self> {
+        output.write_all(&header(seed, start))?;
+
+        Ok(Self {
+            state,
+            output: Some(output),
+            frame: 0,
+            previous_event_frame: 0,
+        })
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn into_inner(self) -> (S, Option<W>) {
+        (self.state, self.output)
+    }
+
+    fn record(&mut self, event: Event) {
+        let Some(output) = &mut self.output else {
+            return
+        };
+
+        let mut bytes = Vec::with_capacity(8);
+        push_event(&mut bytes, self.frame - self.previous_event_frame, event);
+        self.previous_event_frame = self.frame;
+
+        if let Err(e) = output.write_all(&bytes).and_then(|()| output.flush()) {
+            features::log(&format!("stopped recording replay: {e}"));
+            self.output = None;
+        }
+    }
+}
+
+impl<S: State, W: Write> State for Recorder<S, W> {
+    fn frame(&mut self) -> (&[Command], &[SFX]) {
+        self.frame += 1;
+        self.state.frame()
+    }
+
+    fn press(&mut self, button: Button) {
+        self.record(Event::Press(button));
+        self.state.press(button);
+    }
+
+    fn release(&mut self, button: Button) {
+        self.record(Event::Release(button));
+        self.state.release(button);
+    }
+
+    fn typed(&mut self, typed: Typed) {
+        self.record(Event::Typed(typed));
+        self.state.typed(typed);
+    }
+
+    fn quick_save(&mut self) {
+        self.record(Event::QuickSave);
+        self.state.quick_save();
+    }
+
+    fn quick_load(&mut self) {
+        self.record(Event::QuickLoad);
+        self.state.quick_load();
+    }
+}
+
+/// Feeds the events of a replay to the wrapped state on the frames they were
+/// recorded on. Live input is ignored until the replay is finished, since it
+/// would make the session diverge from the recording.
+pub struct Replayer<S> {
+    state: S,
+    events: Vec<(FrameIndex, Event)>,
+    next_event: usize,
+    frame: FrameIndex,
+}
+
+impl<S: State> Replayer<S> {
+    /// `state` should have been set up from the seed and the start of
+    /// `replay`.
+    pub fn new(state: S, replay: Replay) -> Self {
+        Self {
+            state,
+            events: replay.events,
+            next_event: 0,
+            frame: 0,
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    pub fn into_inner(self) -> S {
+        self.state
+    }
+
+    /// Returns true once every event in the replay has been fed to the state.
+    pub fn is_finished(&self) -> bool {
+        self.next_event >= self.events.len()
+    }
+
+    /// Runs frames until every event in the replay has happened, without
+    /// needing a window.
+    pub fn run_to_end(&mut self) {
+        while !self.is_finished() {
+            self.frame();
+        }
+    }
+}
+
+impl<S: State> State for Replayer<S> {
+    fn frame(&mut self) -> (&[Command], &[SFX]) {
+        while let Some(&(frame, event)) = self.events.get(self.next_event) {
+            if frame > self.frame {
+                break
+            }
+            self.next_event += 1;
+
+            match event {
+                Event::Press(button) => self.state.press(button),
+                Event::Release(button) => self.state.release(button),
+                Event::Typed(typed) => self.state.typed(typed),
+                Event::QuickSave => self.state.quick_save(),
+                Event::QuickLoad => self.state.quick_load(),
+            }
+        }
+
+        self.frame += 1;
+        self.state.frame()
+    }
+
+    fn press(&mut self, button: Button) {
+        if self.is_finished() {
+            self.state.press(button);
+        }
+    }
+
+    fn release(&mut self, button: Button) {
+        if self.is_finished() {
+            self.state.release(button);
+        }
+    }
+
+    fn typed(&mut self, typed: Typed) {
+        if self.is_finished() {
+            self.state.typed(typed);
+        }
+    }
+
+    fn quick_save(&mut self) {
+        if self.is_finished() {
+            self.state.quick_save();
+        }
+    }
+
+    fn quick_load(&mut self) {
+        if self.is_finished() {
+            self.state.quick_load();
+        }
+    }
+}
+
+#[cfg(test)]
+mod recording_then_replaying {
+    use super::*;
+
+    /// Writes down what it was told to do, and when.
+    #[derive(Default)]
+    struct Log {
+        frame: FrameIndex,
+        events: Vec<(FrameIndex, Event)>,
+    }
+
+    impl State for Log {
+        fn frame(&mut self) -> (&[Command], &[SFX]) {
+            self.frame += 1;
+            (&[], &[])
+        }
+
+        fn press(&mut self, button: Button) {
+            self.events.push((self.frame, Event::Press(button)));
+        }
+
+        fn release(&mut self, button: Button) {
+            self.events.push((self.frame, Event::Release(button)));
+        }
+
+        fn quick_save(&mut self) {
+            self.events.push((self.frame, Event::QuickSave));
+        }
+    }
+
+    #[test]
+    fn feeds_the_same_events_on_the_same_frames() {
+        let seed = [42; 16];
+        let start = b"level 3";
+        let mut recorder = Recorder::new(Log::default(), seed, start, Vec::new()).unwrap();
+
+        recorder.frame();
+        recorder.press(Button::START);
+        recorder.frame();
+        recorder.release(Button::START);
+        for _ in 0..200 {
+            recorder.frame();
+        }
+        recorder.press(Button::UP);
+        recorder.press(Button::LEFT);
+        recorder.quick_save();
+        recorder.frame();
+
+        let (recorded, bytes) = recorder.into_inner();
+        let replay = Replay::from_bytes(&bytes.unwrap()).unwrap();
+        assert_eq!(replay.seed, seed);
+        assert_eq!(replay.start, start);
+
+        let mut replayer = Replayer::new(Log::default(), replay);
+        replayer.run_to_end();
+
+        assert_eq!(replayer.into_inner().events, recorded.events);
+    }
+
+    #[test]
+    fn to_bytes_then_from_bytes_is_identity() {
+        let expected = Replay {
+            seed: [7; 16],
+            start: vec![1, 2, 3],
+            events: vec![
+                (0, Event::Press(Button::A)),
+                (0, Event::Release(Button::A)),
+                (1_000_000, Event::QuickLoad),
+                (1_000_000, Event::Typed(Typed::Char('é'))),
+                (1_000_001, Event::Typed(Typed::Backspace)),
+            ],
+        };
+
+        let actual = Replay::from_bytes(&expected.to_bytes()).unwrap();
+
+        assert_eq!(expected, actual);
+    }
+}
@@ -0,0 +1,456 @@
+//! Runs a `platform_types::State` in a terminal, for when there is no window
+//! to open, like over SSH.
+//!
+//! Text drawn with the font is shown as terminal text. Everything else is
+//! rendered as usual, then shown two pixels to a character cell, using the
+//! upper half block character with truecolour escape codes.
+
+use platform_types::{
+    command,
+    Button,
+    Command,
+    State,
+    Typed,
+    ARGB,
+    BLACK,
+    WHITE,
+    FONT_BASE_Y,
+    FONT_WIDTH,
+};
+use render::FrameBuffer;
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    style::{self, Color},
+    terminal::{self, ClearType},
+    QueueableCommand,
+};
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const FRAME_DURATION: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+/// The size in pixels of a character in the font, which `gfx` also uses.
+const CHAR_SIZE: u16 = 8;
+
+const UPPER_HALF_BLOCK: char = '▀';
+
+/// Runs `state` until escape or Ctrl-C is pressed, with the same keys as
+/// `platform::run`, except that right shift is replaced by tab or space, since
+/// terminals do not report shift on its own.
+pub fn run<S: State>(mut state: S) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout();
+    let guard = Guard::new(&mut stdout)?;
+
+    let mut frame_buffer = FrameBuffer::from_size((command::WIDTH, command::HEIGHT));
+    let mut previous_screen = None;
+    // Most terminals only tell us about key presses, so we release each
+    // pressed button after one frame, and rely on key repeat for held keys.
+    let mut to_release = Vec::new();
+    let mut next_frame = Instant::now();
+
+    loop {
+        while let Some(timeout) = next_frame.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break
+            }
+
+            match event::read()? {
+                Event::Key(key) => match handle_key(&mut state, key, guard.reports_releases) {
+                    KeyOutcome::Continue => {},
+                    KeyOutcome::Pressed(button) => to_release.push(button),
+                    KeyOutcome::Logged => previous_screen = None,
+                    KeyOutcome::Quit => return Ok(()),
+                },
+                // Start over, so nothing is left behind where the screen used
+                // to reach.
+                Event::Resize(..) => previous_screen = None,
+                _ => {},
+            }
+        }
+        next_frame = Instant::now() + FRAME_DURATION;
+
+        let (commands, _) = state.frame();
+        let mut screen = Screen::from_commands(commands, &mut frame_buffer);
+
+        // Rather than let the screen spill off the edges, where it would
+        // scroll the terminal and leave a mess behind, say what would fit.
+        let (columns, rows) = terminal::size()?;
+        let (needed_columns, needed_rows) = screen.size();
+        if needed_columns > usize::from(columns) || needed_rows > usize::from(rows) {
+            screen = Screen::text(&format!(
+                "make the terminal at least {needed_columns} columns by {needed_rows} rows to see this (it is {columns} by {rows})"
+            ));
+        }
+
+        for button in to_release.drain(..) {
+            state.release(button);
+        }
+
+        if previous_screen.as_ref() != Some(&screen) {
+            if previous_screen.is_none() {
+                stdout.queue(terminal::Clear(ClearType::All))?;
+            }
+            screen.write_to(previous_screen.as_ref(), &mut stdout)?;
+            stdout.flush()?;
+            previous_screen = Some(screen);
+        }
+    }
+}
+
+/// Puts the terminal into the mode we need, and back again when dropped, even
+/// if we are unwinding from a panic.
+struct Guard {
+    reports_releases: bool,
+}
+
+impl Guard {
+    fn new(stdout: &mut std::io::Stdout) -> std::io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        stdout.queue(terminal::EnterAlternateScreen)?;
+        stdout.queue(cursor::Hide)?;
+
+        // Terminals that support this tell us when keys are released, so
+        // buttons can be held down like they can in a window.
+        let reports_releases = terminal::supports_keyboard_enhancement().unwrap_or(false);
+        if reports_releases {
+            stdout.queue(event::PushKeyboardEnhancementFlags(
+                event::KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            ))?;
+        }
+        stdout.flush()?;
+
+        Ok(Self { reports_releases })
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // If these fail there is nothing better to do than carry on.
+        let mut stdout = std::io::stdout();
+        if self.reports_releases {
+            let _ = stdout.queue(event::PopKeyboardEnhancementFlags);
+        }
+        let _ = stdout.queue(style::ResetColor);
+        let _ = stdout.queue(cursor::Show);
+        let _ = stdout.queue(terminal::LeaveAlternateScreen);
+        let _ = stdout.flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+enum KeyOutcome {
+    Continue,
+    /// The button was pressed, and the terminal will not tell us when it is
+    /// released.
+    Pressed(Button),
+    /// The state may have logged something over the screen.
+    Logged,
+    Quit,
+}
+
+fn handle_key<S: State>(state: &mut S, key: KeyEvent, reports_releases: bool) -> KeyOutcome {
+    let KeyEvent { code, modifiers, kind, .. } = key;
+
+    match (code, kind) {
+        (KeyCode::Esc, KeyEventKind::Press) => return KeyOutcome::Quit,
+        (KeyCode::Char('c'), KeyEventKind::Press)
+        if modifiers.contains(KeyModifiers::CONTROL) => return KeyOutcome::Quit,
+        (KeyCode::F(5), KeyEventKind::Press) => {
+            state.quick_save();
+            return KeyOutcome::Logged
+        },
+        (KeyCode::F(9), KeyEventKind::Press) => {
+            state.quick_load();
+            return KeyOutcome::Logged
+        },
+        _ => {}
+    }
+
+    match (code, kind) {
+        (KeyCode::Char(character), KeyEventKind::Press | KeyEventKind::Repeat) => {
+            state.typed(Typed::Char(character));
+        },
+        (KeyCode::Backspace, KeyEventKind::Press | KeyEventKind::Repeat) => {
+            state.typed(Typed::Backspace);
+        },
+        _ => {}
+    }
+
+    let button = match code {
+        KeyCode::Enter => Button::START,
+        KeyCode::Tab | KeyCode::Char(' ') => Button::SELECT,
+        KeyCode::Up => Button::UP,
+        KeyCode::Left => Button::LEFT,
+        KeyCode::Right => Button::RIGHT,
+        KeyCode::Down => Button::DOWN,
+
+        KeyCode::Char('z' | 'Z') => Button::A,
+        KeyCode::Char('x' | 'X') => Button::B,
+
+        // For those using the Dvorak layout.
+        KeyCode::Char(';') => Button::A,
+        KeyCode::Char('q' | 'Q') => Button::B,
+
+        _ => return KeyOutcome::Continue,
+    };
+
+    match kind {
+        // `State::press` ignores presses of held buttons, so key repeats
+        // only matter when the terminal does not report releases.
+        KeyEventKind::Press | KeyEventKind::Repeat => {
+            state.press(button);
+            if reports_releases {
+                KeyOutcome::Continue
+            } else {
+                KeyOutcome::Pressed(button)
+            }
+        },
+        KeyEventKind::Release => {
+            state.release(button);
+            KeyOutcome::Continue
+        },
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub character: char,
+    pub foreground: ARGB,
+    pub background: ARGB,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            character: ' ',
+            foreground: BLACK,
+            background: BLACK,
+        }
+    }
+}
+
+/// A frame as it is shown in a terminal: a row for each line of text, with
+/// the rest of the frame between them, at the height it was drawn at.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Screen {
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Screen {
+    /// `frame_buffer` should be `command::WIDTH` by `command::HEIGHT`, and
+    /// passed in each time, so unchanged frames need not be rendered again.
+    pub fn from_commands(commands: &[Command], frame_buffer: &mut FrameBuffer) -> Self {
+        struct Character {
+            x: u16,
+            y: u16,
+            character: char,
+            colour: ARGB,
+        }
+
+        let mut characters = Vec::new();
+        let mut drawn = Vec::with_capacity(commands.len());
+        // The smallest rect holding everything that is not text.
+        let mut bounds: Option<(u16, u16, u16, u16)> = None;
+
+        for command in commands {
+            let rect = command.rect.unscaled();
+            let (x, y) = (rect.x.get(), rect.y.get());
+
+            // Only the font is kept that far down the spritesheet. The size
+            // is not checked, since characters at the edge get clipped.
+            if command.sprite_xy.y.0 >= u16::from(FONT_BASE_Y) {
+                const CHARS_PER_ROW: u16 = FONT_WIDTH as u16 / CHAR_SIZE;
+                let index = (command.sprite_xy.y.0 - u16::from(FONT_BASE_Y)) / CHAR_SIZE
+                    * CHARS_PER_ROW
+                    + command.sprite_xy.x.0 / CHAR_SIZE;
+
+                characters.push(Character {
+                    x,
+                    y,
+                    character: match u8::try_from(index) {
+                        Ok(byte) if byte.is_ascii_graphic() => char::from(byte),
+                        _ => ' ',
+                    },
+                    colour: command.colour_override,
+                });
+            } else {
+                let (x_max, y_max) = (x + rect.w.get() - 1, y + rect.h.get() - 1);
+                bounds = Some(match bounds {
+                    None => (x, y, x_max, y_max),
+                    Some((x_min, y_min, old_x_max, old_y_max)) => (
+                        x_min.min(x),
+                        y_min.min(y),
+                        old_x_max.max(x_max),
+                        old_y_max.max(y_max),
+                    ),
+                });
+                drawn.push(*command);
+            }
+        }
+
+        characters.sort_by_key(|c| (c.y, c.x));
+
+        let mut rows = Vec::new();
+        let push_character = |rows: &mut Vec<Vec<Cell>>, c: &Character| {
+            let row = rows.last_mut().expect("a row was pushed for this character");
+            let column = usize::from(c.x / CHAR_SIZE);
+            if row.len() <= column {
+                row.resize(column + 1, Cell::default());
+            }
+            row[column] = Cell {
+                character: c.character,
+                foreground: c.colour,
+                background: BLACK,
+            };
+        };
+
+        let mut characters = characters.iter().peekable();
+        let mut previous_y = None;
+
+        if let Some((x_min, y_min, x_max, y_max)) = bounds {
+            while let Some(c) = characters.next_if(|c| c.y < y_min) {
+                if previous_y != Some(c.y) {
+                    rows.push(Vec::new());
+                    previous_y = Some(c.y);
+                }
+                push_character(&mut rows, c);
+            }
+
+            render::render(frame_buffer, &drawn);
+
+            let width = usize::from(frame_buffer.width);
+            let pixel = |x: u16, y: u16| {
+                if y > y_max {
+                    return BLACK
+                }
+                frame_buffer.buffer
+                    .get(usize::from(y) * width + usize::from(x))
+                    .copied()
+                    .unwrap_or(BLACK)
+            };
+
+            for y in (y_min..=y_max).step_by(2) {
+                rows.push(
+                    (x_min..=x_max)
+                        .map(|x| Cell {
+                            character: UPPER_HALF_BLOCK,
+                            foreground: pixel(x, y),
+                            background: pixel(x, y + 1),
+                        })
+                        .collect()
+                );
+            }
+            previous_y = None;
+        }
+
+        for c in characters {
+            if previous_y != Some(c.y) {
+                rows.push(Vec::new());
+                previous_y = Some(c.y);
+            }
+            push_character(&mut rows, c);
+        }
+
+        Self { rows }
+    }
+
+    /// A screen with just the given line of text on it.
+    pub fn text(text: &str) -> Self {
+        Self {
+            rows: vec![
+                text.chars()
+                    .map(|character| Cell {
+                        character,
+                        foreground: WHITE,
+                        background: BLACK,
+                    })
+                    .collect()
+            ],
+        }
+    }
+
+    /// How many columns and rows it takes to show all of this.
+    pub fn size(&self) -> (usize, usize) {
+        (
+            self.rows.iter().map(Vec::len).max().unwrap_or(0),
+            self.rows.len(),
+        )
+    }
+
+    /// Writes out the rows that differ from `previous`, which should be what
+    /// is on the terminal already, if anything.
+    pub fn write_to(&self, previous: Option<&Screen>, output: &mut impl Write) -> std::io::Result<()> {
+        fn colour(argb: ARGB) -> Color {
+            let [_, r, g, b] = argb.to_be_bytes();
+            Color::Rgb { r, g, b }
+        }
+
+        let old_rows = previous.map(|screen| &screen.rows[..]).unwrap_or_default();
+
+        let mut previous = None;
+        for (i, row) in self.rows.iter().enumerate() {
+            if old_rows.get(i) == Some(row) {
+                continue
+            }
+
+            output.queue(cursor::MoveTo(0, u16::try_from(i).unwrap_or(u16::MAX)))?;
+
+            for cell in row {
+                if previous != Some((cell.foreground, cell.background)) {
+                    output.queue(style::SetColors(style::Colors::new(
+                        colour(cell.foreground),
+                        colour(cell.background),
+                    )))?;
+                    previous = Some((cell.foreground, cell.background));
+                }
+                output.queue(style::Print(cell.character))?;
+            }
+
+            output.queue(style::ResetColor)?;
+            previous = None;
+            output.queue(terminal::Clear(ClearType::UntilNewLine))?;
+        }
+
+        if old_rows.len() > self.rows.len() {
+            output.queue(cursor::MoveTo(0, u16::try_from(self.rows.len()).unwrap_or(u16::MAX)))?;
+            output.queue(terminal::Clear(ClearType::FromCursorDown))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod from_commands {
+    use super::*;
+    use platform_types::{unscaled, PALETTE};
+
+    #[test]
+    fn puts_text_above_the_pixels_above_it() {
+        let mut commands = gfx::Commands::default();
+        commands.print(b"hi!", unscaled::X(16), unscaled::Y(0), 6);
+        commands.draw_pixel(unscaled::X(100), unscaled::Y(20), 1);
+        commands.draw_pixel(unscaled::X(101), unscaled::Y(21), 2);
+        commands.print(b"below", unscaled::X(0), unscaled::Y(30), 3);
+
+        let mut frame_buffer = FrameBuffer::from_size((command::WIDTH, command::HEIGHT));
+        let screen = Screen::from_commands(commands.slice(), &mut frame_buffer);
+
+        let text = |row: &[Cell]| row.iter().map(|cell| cell.character).collect::<String>();
+
+        assert_eq!(screen.rows.len(), 3);
+        assert_eq!(text(&screen.rows[0]), "  hi!");
+        assert_eq!(screen.rows[0][2].foreground, PALETTE[6]);
+        assert_eq!(
+            screen.rows[1],
+            vec![
+                Cell { character: UPPER_HALF_BLOCK, foreground: PALETTE[1], background: BLACK },
+                Cell { character: UPPER_HALF_BLOCK, foreground: BLACK, background: PALETTE[2] },
+            ]
+        );
+        assert_eq!(text(&screen.rows[2]), "below");
+    }
+}
@@ -86,3 +86,21 @@ pub fn from_seed(mut seed: Seed) -> Xs {
         wrap!(12, 13, 14, 15),
     ]
 }
+
+/// Parses up to 32 hex digits into a seed. Shorter strings are padded with
+/// leading zeroes, so `"1"` works.
+pub fn seed_from_hex(hex: &str) -> Option<Seed> {
+    if hex.is_empty()
+    || hex.len() > 32
+    || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let padded = format!("{hex:0>32}");
+    let mut seed = Seed::default();
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(padded.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+
+    Some(seed)
+}
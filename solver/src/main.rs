@@ -0,0 +1,170 @@
+const USAGE: &str = "\
+usage: solver [OPTIONS] LEVEL_FILE
+       solver [OPTIONS] --generate CODE
+
+Looks for a shortest way to win the level in LEVEL_FILE without causing a
+paradox, then prints it out. Every move, wait and jump counts as one action.
+If it finds none, every way of playing within the limits was tried, unless
+it says it ran out of states first.
+
+options:
+    --rules RULES       paradox, branching or self-consistent (defaults to
+                        paradox, like the game)
+    --max-jumps N       only try plans with up to N jumps (defaults to 3)
+    --max-instant N     only try plans that stay within instant N (defaults to 64)
+    --max-states N      give up after trying N states, which take a couple of
+                        hundred bytes each (defaults to 2000000)
+    --script PATH       write the solution to this file, as a headless script
+    --replay PATH       write the solution to this file, as a replay
+    --generate CODE     solve the level generated from this code, like 2-1f3a,
+                        instead of a level file
+    --write-level PATH  write the level to this file, which is handy along with
+                        --generate
+    --help              print this message
+
+The script starts on the first frame after the level is loaded, so play it
+back with --level LEVEL_FILE, under the rules it was found for. The replay
+holds the level and the rules, so it plays back on its own.";
+
+fn main() {
+    match run() {
+        Ok(true) => {},
+        Ok(false) => std::process::exit(1),
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(2);
+        },
+    }
+}
+
+/// Whether a solution was found.
+fn run() -> Result<bool, String> {
+    let mut rules = game::Rules::default();
+    let mut limits = solver::Limits::default();
+    let mut script_path = None;
+    let mut replay_path = None;
+    let mut level_path = None;
+    let mut code = None;
+    let mut write_level_path = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+
+        match arg.as_str() {
+            "--rules" => {
+                rules = match value()?.as_str() {
+                    "paradox" => game::Rules::Paradox,
+                    "branching" => game::Rules::Branching,
+                    "self-consistent" => game::Rules::SelfConsistent,
+                    other => return Err(format!(
+                        "unknown rules: {other}; expected paradox, branching or self-consistent"
+                    )),
+                };
+            },
+            "--max-jumps" => {
+                let n = value()?;
+                limits.max_jumps = n.parse().map_err(|_| format!("invalid jump count: {n}"))?;
+            },
+            "--max-instant" => {
+                let n = value()?;
+                limits.max_instant = n.parse().map_err(|_| format!("invalid instant: {n}"))?;
+            },
+            "--max-states" => {
+                let n = value()?;
+                limits.max_states = n.parse().map_err(|_| format!("invalid state count: {n}"))?;
+            },
+            "--script" => script_path = Some(value()?),
+            "--replay" => replay_path = Some(value()?),
+            "--generate" => {
+                let text = value()?;
+                code = Some(
+                    solver::generate::Code::parse(&text, xs::Seed::default())
+                        .ok_or_else(|| format!("invalid code: {text}"))?
+                );
+            },
+            "--write-level" => write_level_path = Some(value()?),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                return Ok(true)
+            },
+            _ if !arg.starts_with('-') && level_path.is_none() => level_path = Some(arg),
+            _ => return Err(format!("unknown argument: {arg}\n{USAGE}")),
+        }
+    }
+
+    let level = match (level_path, code) {
+        (Some(_), Some(_)) => return Err(format!("--generate cannot be used with a level file\n{USAGE}")),
+        (None, None) => return Err(format!("no level file given\n{USAGE}")),
+        (Some(level_path), None) => {
+            let text = std::fs::read_to_string(&level_path)
+                .map_err(|e| format!("could not read {level_path}: {e}"))?;
+            game::level_file::parse(&text)
+                .map_err(|e| format!("{level_path}: {e}"))?
+        },
+        (None, Some(code)) => solver::generate::level(code)
+            .ok_or_else(|| format!("could not generate a level from {code}, try another code"))?,
+    };
+
+    if let Some(path) = write_level_path {
+        std::fs::write(&path, game::level_file::write(&level))
+            .map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    let actions = match solver::solve(&level, rules, limits) {
+        Ok(actions) => actions,
+        Err(solver::Unsolved::NoSolution) => {
+            println!(
+                "no solution within {} jumps / instant {}",
+                limits.max_jumps,
+                limits.max_instant,
+            );
+            return Ok(false)
+        },
+        Err(solver::Unsolved::TooManyStates) => {
+            println!(
+                "gave up after {} states without finding a solution; \
+                 try a higher --max-states, or lower --max-jumps or --max-instant",
+                limits.max_states,
+            );
+            return Ok(false)
+        },
+    };
+
+    println!("solved {} in {} actions:", level.name, actions.len());
+    let mut i = 0;
+    while let Some(&action) = actions.get(i) {
+        let run = actions[i..].iter().take_while(|&&a| a == action).count();
+        match action {
+            game::Action::Jump { from, to } => println!("    jump from {from} to {to}"),
+            game::Action::Move(dir) if run == 1 => println!("    move {dir:?}"),
+            game::Action::Move(dir) => println!("    move {dir:?} {run} times"),
+            game::Action::Wait if run == 1 => println!("    wait"),
+            game::Action::Wait => println!("    wait {run} times"),
+        }
+        i += run;
+    }
+
+    let events = solver::events(&actions);
+
+    if let Some(path) = script_path {
+        std::fs::write(&path, solver::script(&events))
+            .map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    if let Some(path) = replay_path {
+        let seed = xs::Seed::default();
+        let mut state = app::State::new((seed, None, None));
+        state.game_state.rules = rules;
+        state.game_state.load_level(&level);
+        let replay = replay::Replay {
+            seed,
+            start: state.start_bytes(),
+            events,
+        };
+        std::fs::write(&path, replay.to_bytes())
+            .map_err(|e| format!("could not write {path}: {e}"))?;
+    }
+
+    Ok(true)
+}
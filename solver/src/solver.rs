@@ -0,0 +1,1151 @@
+//! Searches for a shortest way to win a level without causing a paradox, by
+//! trying out moves and jumps on a `game::State`.
+//!
+//! The search is A* over the states the game can be in, counting each step
+//! or jump as one action. States are told apart by where the player is, the
+//! current instant, how many jumps there have been, and every splat on the
+//! timeline, so a state reached again by some other route is skipped. Every
+//! action only ever adds to the timeline, so rather than keeping a copy of
+//! it for every state, each state keeps the splat it added, if any, and the
+//! timeline is played back from those when needed.
+//!
+//! The estimate of how many actions are left never overestimates, so the
+//! first win found is a shortest one, and running out of states means there
+//! is no solution within the limits at all. See `Estimate` for how it works.
+//! Some levels have far too many states to try them all, so the search also
+//! gives up after a set number of them, and says so.
+
+use game::{
+    xy, Action, Cell, Channel, Dir, InstantIndex, Level, Player, Rules, SelfId, Splat, State,
+    Terrain, TimeMode, Timeline, TERRAIN_CELL_COUNT, TERRAIN_H, TERRAIN_W,
+};
+use platform_types::{Button, Typed};
+use replay::{Event, FrameIndex};
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// How far the search goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// The most jumps a solution can have.
+    pub max_jumps: u32,
+    /// Solutions never let time flow past this instant, or jump past it.
+    pub max_instant: InstantIndex,
+    /// The search gives up after reaching this many states. Each one takes
+    /// up a couple of hundred bytes.
+    pub max_states: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_jumps: 3,
+            max_instant: 64,
+            max_states: 2_000_000,
+        }
+    }
+}
+
+/// Why `solve` did not come up with a solution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unsolved {
+    /// Every way of playing within the limits was tried, and none of them
+    /// won without causing a paradox.
+    NoSolution,
+    /// The search reached `Limits::max_states` first, so there may or may
+    /// not be a solution.
+    TooManyStates,
+}
+
+/// The actions that win the level in as few actions as possible, starting
+/// from when it is loaded, if there are any within the limits.
+pub fn solve(level: &Level, rules: Rules, limits: Limits) -> Result<Vec<Action>, Unsolved> {
+    let mut probe = State {
+        rules,
+        ..<_>::default()
+    };
+    probe.load_level(level);
+
+    let estimate = Estimate::new(&probe.terrain);
+    let last_instant = core::cmp::min(limits.max_instant, probe.budget.last_instant);
+
+    let mut nodes = vec![Node {
+        parent: None,
+        action: Action::Wait,
+        splat: None,
+        player: probe.player,
+        current: probe.current,
+        self_id: probe.self_id,
+        jumps: 0,
+        opened: [0; MAX_CHANNELS],
+        own: [0; MAX_CHANNELS],
+        actions: 0,
+        others: 0,
+        trail: 0,
+        won: false,
+    }];
+    let mut best = HashMap::from([(nodes[0].key(limits.max_jumps), 0)]);
+    let mut frontier = BinaryHeap::new();
+    if let Some(remaining) = estimate.remaining(&nodes[0], limits.max_jumps) {
+        frontier.push(Reverse((remaining, Reverse(0), 0, Expansion::Steps)));
+    }
+
+    while let Some(Reverse((_, _, index, expansion))) = frontier.pop() {
+        let node = nodes[index];
+        if node.won {
+            return Ok(actions_to(&nodes, index))
+        }
+        if nodes.len() > limits.max_states {
+            return Err(Unsolved::TooManyStates)
+        }
+
+        let children = match expansion {
+            Expansion::Steps => {
+                if best.get(&node.key(limits.max_jumps)).is_some_and(|&actions| actions < node.actions) {
+                    continue
+                }
+
+                // There are a lot of jumps from here, and most of them are
+                // never needed, so rather than adding them all now, they are
+                // only tried once the search gets as far as the soonest any
+                // of them could win.
+                if node.jumps < limits.max_jumps {
+                    let jumped = node.jumped();
+                    if let Some(remaining) = estimate.remaining(&jumped, limits.max_jumps) {
+                        frontier.push(Reverse((
+                            jumped.actions + remaining,
+                            Reverse(jumped.actions),
+                            index,
+                            Expansion::Jumps,
+                        )));
+                    }
+                }
+
+                load(&mut probe, &nodes, index);
+                steps(&mut probe, &node, index, last_instant, &estimate)
+            },
+            Expansion::Jumps => {
+                load(&mut probe, &nodes, index);
+                jumps(&mut probe, &node, index, last_instant)
+            },
+        };
+
+        for child in children {
+            let Some(remaining) = estimate.remaining(&child, limits.max_jumps) else { continue };
+            if !child.won {
+                let key = child.key(limits.max_jumps);
+                if best.get(&key).is_some_and(|&actions| actions <= child.actions) {
+                    continue
+                }
+                best.insert(key, child.actions);
+            }
+
+            frontier.push(Reverse((
+                child.actions + remaining,
+                Reverse(child.actions),
+                nodes.len(),
+                Expansion::Steps,
+            )));
+            nodes.push(child);
+        }
+    }
+
+    Err(Unsolved::NoSolution)
+}
+
+/// What to try from a node when it comes off the frontier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Expansion {
+    Steps,
+    Jumps,
+}
+
+type Opened = [InstantIndex; MAX_CHANNELS];
+
+fn bits(opened: &Opened) -> u32 {
+    opened.iter()
+        .enumerate()
+        .filter(|&(_, &until)| until > 0)
+        .fold(0, |output, (i, _)| output | 1 << i)
+}
+
+/// A state the search has reached, along with how it got there.
+#[derive(Clone, Copy, Debug)]
+struct Node {
+    parent: Option<usize>,
+    /// What was done to get here from the parent.
+    action: Action,
+    /// The splat the action added to the timeline, if any.
+    splat: Option<(InstantIndex, Splat)>,
+    player: Player,
+    current: InstantIndex,
+    self_id: SelfId,
+    jumps: u32,
+    /// For each channel in `Estimate::channels`, one past the last instant
+    /// that a self other than the current one opens it at, or 0 if none do.
+    opened: Opened,
+    /// The same for the current self, whose plates do not help it until
+    /// after a jump, when it becomes a past self.
+    own: Opened,
+    actions: u32,
+    /// A hash of every splat left by the selves before the current one,
+    /// which does not depend on the order they were added in.
+    others: u64,
+    /// The same for the splats left by the current self.
+    trail: u64,
+    won: bool,
+}
+
+impl Node {
+    /// The node after a jump from this one, apart from where it jumps to,
+    /// which is left as the start, where winning looks the closest.
+    fn jumped(&self) -> Node {
+        Node {
+            splat: None,
+            current: 0,
+            jumps: self.jumps + 1,
+            opened: core::array::from_fn(|i| core::cmp::max(self.opened[i], self.own[i])),
+            others: self.others.wrapping_add(self.trail),
+            trail: 0,
+            own: [0; MAX_CHANNELS],
+            actions: self.actions + 1,
+            ..*self
+        }
+    }
+
+    /// Nodes with the same key have the same actions ahead of them.
+    fn key(&self, max_jumps: u32) -> (usize, InstantIndex, u32, u64, u64) {
+        // Nobody ever meets their own past splats, so once there are no
+        // jumps left to turn them into someone else's, they do not matter.
+        // Except that under the self-consistent rules, the game looks at
+        // where the player was at the instant before, which is where they
+        // are now, unless they just arrived.
+        let trail = if self.jumps < max_jumps {
+            self.trail
+        } else {
+            u64::from(self.splat.is_none())
+        };
+
+        (cell_index(self.player), self.current, self.jumps, self.others, trail)
+    }
+}
+
+fn splat_hash(instant: InstantIndex, splat: &Splat) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (instant, splat.x.get().get(), splat.y.get().get(), splat.self_id).hash(&mut hasher);
+    hasher.finish()
+}
+
+fn actions_to(nodes: &[Node], mut index: usize) -> Vec<Action> {
+    let mut output = Vec::new();
+    while let Some(parent) = nodes[index].parent {
+        output.push(nodes[index].action);
+        index = parent;
+    }
+    output.reverse();
+    output
+}
+
+/// Puts the probe into the state of the given node.
+fn load(probe: &mut State, nodes: &[Node], index: usize) {
+    let mut splats = Vec::new();
+    let mut at = Some(index);
+    while let Some(i) = at {
+        splats.extend(nodes[i].splat);
+        at = nodes[i].parent;
+    }
+
+    // Splats at the same instant need to be in the order they were added.
+    probe.instants = Timeline::default();
+    for &(instant, splat) in splats.iter().rev() {
+        probe.instants.push(instant, splat);
+    }
+
+    let node = &nodes[index];
+    probe.player = node.player;
+    probe.current = node.current;
+    probe.self_id = node.self_id;
+    probe.last_self_id = node.self_id;
+    probe.time_mode = TimeMode::Flowing;
+    probe.rewind = None;
+}
+
+const CHOICES: [Option<Dir>; 9] = [
+    Some(Dir::Up),
+    Some(Dir::Down),
+    Some(Dir::Left),
+    Some(Dir::Right),
+    Some(Dir::UpLeft),
+    Some(Dir::UpRight),
+    Some(Dir::DownLeft),
+    Some(Dir::DownRight),
+    None,
+];
+
+/// Every state that can be reached from the node with a single step, using
+/// the game's own rules, on a probe that is in the node's state. The probe
+/// is put back the way it was after each step, which is much cheaper than
+/// copying it.
+fn steps(
+    probe: &mut State,
+    node: &Node,
+    index: usize,
+    last_instant: InstantIndex,
+    estimate: &Estimate,
+) -> Vec<Node> {
+    let mut output = Vec::new();
+    // Time cannot flow past the last instant, so there are no steps from
+    // there.
+    if node.current >= last_instant {
+        return output
+    }
+
+    let child = Node {
+        parent: Some(index),
+        actions: node.actions + 1,
+        ..*node
+    };
+
+    for dir in CHOICES {
+        probe.step(dir);
+
+        let advanced = probe.current == node.current + 1;
+        let splat = if advanced {
+            probe.instants.pop(node.current)
+        } else {
+            None
+        };
+
+        match (probe.time_mode, splat) {
+            (TimeMode::Flowing | TimeMode::Won, Some(splat)) => {
+                output.push(Node {
+                    action: match dir {
+                        Some(dir) => Action::Move(dir),
+                        None => Action::Wait,
+                    },
+                    splat: Some((node.current, splat)),
+                    player: probe.player,
+                    current: probe.current,
+                    own: match estimate.opens(probe.player) {
+                        Some(i) => {
+                            let mut own = node.own;
+                            own[i] = node.current + 1;
+                            own
+                        },
+                        None => node.own,
+                    },
+                    trail: node.trail.wrapping_add(splat_hash(node.current, &splat)),
+                    won: probe.time_mode == TimeMode::Won,
+                    ..child
+                });
+            },
+            // Moving onto the goal wins even when time cannot flow.
+            (TimeMode::Won, None) => if let Some(dir) = dir {
+                output.push(Node {
+                    action: Action::Move(dir),
+                    won: true,
+                    ..child
+                });
+            },
+            _ => {},
+        }
+
+        probe.player = node.player;
+        probe.current = node.current;
+        probe.time_mode = TimeMode::Flowing;
+        probe.rewind = None;
+    }
+
+    output
+}
+
+/// Every state that can be reached from the node with a single jump, on a
+/// probe that is in the node's state, like `steps`.
+fn jumps(
+    probe: &mut State,
+    node: &Node,
+    index: usize,
+    last_instant: InstantIndex,
+) -> Vec<Node> {
+    let mut output = Vec::new();
+    let child = Node {
+        parent: Some(index),
+        ..node.jumped()
+    };
+
+    for to in 0..=last_instant {
+        if to == node.current {
+            continue
+        }
+
+        probe.jump_to(to);
+        if probe.time_mode == TimeMode::Flowing {
+            output.push(Node {
+                action: Action::Jump { from: node.current, to },
+                current: to,
+                self_id: probe.self_id,
+                ..child
+            });
+        }
+
+        probe.current = node.current;
+        probe.self_id = node.self_id;
+        probe.last_self_id = node.self_id;
+        probe.time_mode = TimeMode::Flowing;
+        probe.rewind = None;
+        // Under the branching rules, jumping back forks a branch, which
+        // starts as a copy of this one.
+        probe.branches = <_>::default();
+    }
+
+    output
+}
+
+/// A lower bound on how many more actions it takes to win, worked out ahead
+/// of time for every cell of the grid.
+///
+/// Walls always block, and doors block unless their channel is open. A door
+/// counts as open if someone other than the current self opens it at any
+/// instant, rather than at the instant it is walked through, which only
+/// makes routes shorter. Any other door has to be opened by a self that
+/// goes by one of its plates first, then jumps, so the bound is the length
+/// of the shortest route to the goal that only goes through a shut door
+/// after going by one of its plates. The jumps count for nothing, since one
+/// self can go by several plates before jumping.
+struct Estimate {
+    cells: Vec<Cell>,
+    /// The channels worth telling apart. When there are too many of them,
+    /// this is left empty and every door counts as open.
+    channels: Vec<Channel>,
+    /// For each set of open channels, as bits, the fewest actions to win
+    /// from each cell.
+    actions: Vec<Vec<u32>>,
+    /// For each set of open channels, the area each cell is in, when the
+    /// other doors are shut, or `NONE` for cells that block.
+    areas: Vec<Vec<u32>>,
+    /// For each set of open channels and each area, whether the goal is in
+    /// it, and the channels of the plates in it.
+    area_contents: Vec<Vec<(bool, u32)>>,
+    /// For each channel, how many steps it takes from each cell to get to
+    /// one of its doors, going through any other door.
+    door_distances: Vec<Vec<u32>>,
+}
+
+const NONE: u32 = u32::MAX;
+
+/// With more channels than this, working out every combination of them
+/// would take longer than it saves.
+const MAX_CHANNELS: usize = 6;
+
+fn cell_index(player: Player) -> usize {
+    usize::from(player.y.get().get()) * TERRAIN_W + usize::from(player.x.get().get())
+}
+
+fn neighbours(index: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = ((index % TERRAIN_W) as isize, (index / TERRAIN_W) as isize);
+    (-1..=1)
+        .flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+        .filter(move |&(nx, ny)| (nx, ny) != (x, y)
+            && (0..TERRAIN_W as isize).contains(&nx)
+            && (0..TERRAIN_H as isize).contains(&ny))
+        .map(|(nx, ny)| ny as usize * TERRAIN_W + nx as usize)
+}
+
+impl Estimate {
+    fn new(terrain: &Terrain) -> Self {
+        let cells: Vec<Cell> = (0..TERRAIN_CELL_COUNT)
+            .map(|i| terrain.get(
+                xy::x((i % TERRAIN_W) as xy::Inner),
+                xy::y((i / TERRAIN_W) as xy::Inner),
+            ))
+            .collect();
+
+        let mut channels: Vec<Channel> = cells.iter()
+            .filter_map(|&cell| match cell {
+                Cell::Plate(channel) | Cell::Door(channel) => Some(channel),
+                _ => None,
+            })
+            .collect();
+        channels.sort_unstable();
+        channels.dedup();
+        if channels.len() > MAX_CHANNELS {
+            channels.clear();
+        }
+
+        let sets = 1 << channels.len();
+        let mut output = Estimate {
+            cells,
+            channels,
+            actions: vec![Vec::new(); sets],
+            areas: vec![Vec::new(); sets],
+            area_contents: vec![Vec::new(); sets],
+            door_distances: Vec::new(),
+        };
+
+        output.door_distances = output.channels.iter()
+            .map(|&channel| output.door_distance(channel))
+            .collect();
+
+        // Each set of channels depends on the sets with one more channel
+        // open, which come after it.
+        for open in (0..sets as u32).rev() {
+            output.actions[open as usize] = output.actions_with(open);
+            let (areas, contents) = output.areas_with(open);
+            output.areas[open as usize] = areas;
+            output.area_contents[open as usize] = contents;
+        }
+
+        output
+    }
+
+    fn bit(&self, channel: Channel) -> u32 {
+        self.channels.iter().position(|&c| c == channel).map_or(0, |i| 1 << i)
+    }
+
+    fn passable(&self, index: usize, open: u32) -> bool {
+        match self.cells[index] {
+            Cell::Wall => false,
+            Cell::Door(channel) => self.bit(channel) & !open == 0,
+            _ => true,
+        }
+    }
+
+    /// Works backwards from the goal, and from the plates for the channels
+    /// that are not open yet, which start off as far from winning as they
+    /// are with their channel open too.
+    fn actions_with(&self, open: u32) -> Vec<u32> {
+        let mut frontier = BinaryHeap::new();
+        for (i, &cell) in self.cells.iter().enumerate() {
+            match cell {
+                Cell::Goal => frontier.push(Reverse((0, i))),
+                Cell::Plate(channel) if self.bit(channel) & !open != 0 => {
+                    let after = self.actions[(open | self.bit(channel)) as usize][i];
+                    if after != NONE {
+                        frontier.push(Reverse((after, i)));
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        let mut output = vec![NONE; TERRAIN_CELL_COUNT];
+        while let Some(Reverse((actions, i))) = frontier.pop() {
+            if actions >= output[i] {
+                continue
+            }
+            output[i] = actions;
+
+            // The player can be in a doorway while the door is open, but the
+            // door still counts as shut for going anywhere through it.
+            if !self.passable(i, open) {
+                continue
+            }
+            for n in neighbours(i) {
+                if actions + 1 < output[n] {
+                    frontier.push(Reverse((actions + 1, n)));
+                }
+            }
+        }
+
+        output
+    }
+
+    fn door_distance(&self, channel: Channel) -> Vec<u32> {
+        let mut output = vec![NONE; TERRAIN_CELL_COUNT];
+        let mut frontier = std::collections::VecDeque::new();
+        for (i, &cell) in self.cells.iter().enumerate() {
+            if cell == Cell::Door(channel) {
+                output[i] = 0;
+                frontier.push_back(i);
+            }
+        }
+
+        while let Some(i) = frontier.pop_front() {
+            for n in neighbours(i) {
+                if output[n] == NONE && !self.cells[n].blocks() {
+                    output[n] = output[i] + 1;
+                    frontier.push_back(n);
+                }
+            }
+        }
+
+        output
+    }
+
+    fn areas_with(&self, open: u32) -> (Vec<u32>, Vec<(bool, u32)>) {
+        let mut areas = vec![NONE; TERRAIN_CELL_COUNT];
+        let mut contents = Vec::new();
+
+        for start in 0..TERRAIN_CELL_COUNT {
+            if areas[start] != NONE || !self.passable(start, open) {
+                continue
+            }
+
+            let area = contents.len() as u32;
+            let mut content = (false, 0);
+            areas[start] = area;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                match self.cells[i] {
+                    Cell::Goal => content.0 = true,
+                    Cell::Plate(channel) => content.1 |= self.bit(channel),
+                    _ => {},
+                }
+                for n in neighbours(i) {
+                    if areas[n] == NONE && self.passable(n, open) {
+                        areas[n] = area;
+                        stack.push(n);
+                    }
+                }
+            }
+            contents.push(content);
+        }
+
+        (areas, contents)
+    }
+
+    /// The index in `channels` of the plate the player is on, if any.
+    fn opens(&self, player: Player) -> Option<usize> {
+        match self.cells[cell_index(player)] {
+            Cell::Plate(channel) => self.channels.iter().position(|&c| c == channel),
+            _ => None,
+        }
+    }
+
+    /// A lower bound on how many jumps it takes to win from the cell. Each
+    /// jump can at best open the doors for every plate the player can get
+    /// to beforehand.
+    fn jumps_with(&self, index: usize, mut open: u32) -> u32 {
+        let mut output = 0;
+        loop {
+            let area = self.areas[open as usize][index];
+            // Someone in a doorway is not in any area, which there is no
+            // need to be clever about.
+            if area == NONE {
+                return 0
+            }
+
+            let (goal, plates) = self.area_contents[open as usize][area as usize];
+            if goal {
+                return output
+            }
+            if plates & !open == 0 {
+                return NONE
+            }
+            open |= plates;
+            output += 1;
+        }
+    }
+
+    /// A lower bound on how many more actions it takes to win from the node,
+    /// or `None` if it cannot be won with the jumps that are left.
+    fn remaining(&self, node: &Node, max_jumps: u32) -> Option<u32> {
+        if node.won {
+            return Some(0)
+        }
+
+        let index = cell_index(node.player);
+        let all = bits(&node.opened) | bits(&node.own);
+        let jumps_left = max_jumps - node.jumps;
+
+        // Without jumping again, the only doors that help are the ones that
+        // others open at some instant the player could get to them by. The
+        // next step is recorded at the current instant, so the soonest the
+        // player can be in a doorway is the instant before the current one,
+        // plus how far away it is.
+        let soon = (0..self.channels.len())
+            .filter(|&i| node.opened[i] >= node.current.saturating_add(self.door_distances[i][index]))
+            .fold(0, |output, i| output | 1 << i);
+
+        let jumps = match self.jumps_with(index, soon) {
+            0 => 0,
+            _ => core::cmp::max(1, self.jumps_with(index, all)),
+        };
+        if jumps > jumps_left {
+            return None
+        }
+
+        // Either no more jumps, in which case the plates the current self
+        // goes by do not help, or at least one, after which they do, at any
+        // instant.
+        let staying = self.actions[soon as usize][index];
+        let jumping = if jumps_left > 0 {
+            self.actions[all as usize][index].saturating_add(1)
+        } else {
+            NONE
+        };
+
+        Some(core::cmp::min(staying, jumping)).filter(|&actions| actions != NONE)
+    }
+}
+fn buttons_of(dir: Dir) -> &'static [Button] {
+    match dir {
+        Dir::Up => &[Button::UP],
+        Dir::Down => &[Button::DOWN],
+        Dir::Left => &[Button::LEFT],
+        Dir::Right => &[Button::RIGHT],
+        Dir::UpLeft => &[Button::UP, Button::LEFT],
+        Dir::UpRight => &[Button::UP, Button::RIGHT],
+        Dir::DownLeft => &[Button::DOWN, Button::LEFT],
+        Dir::DownRight => &[Button::DOWN, Button::RIGHT],
+    }
+}
+
+/// The input that plays out the given actions, starting on the first frame
+/// after a level is loaded. Each step takes a frame, with both buttons
+/// pressed at once for a diagonal move, and each jump takes three: one to
+/// start manipulating time, one to type in the instant, and one to jump.
+pub fn events(actions: &[Action]) -> Vec<(FrameIndex, Event)> {
+    let mut output = Vec::new();
+    let mut frame: FrameIndex = 0;
+    let mut held: &[Button] = &[];
+
+    for &action in actions {
+        for &button in core::mem::take(&mut held) {
+            output.push((frame, Event::Release(button)));
+        }
+
+        match action {
+            Action::Wait => {},
+            Action::Move(dir) => {
+                held = buttons_of(dir);
+                for &button in held {
+                    output.push((frame, Event::Press(button)));
+                }
+            },
+            Action::Jump { to, .. } => {
+                output.push((frame, Event::Press(Button::START)));
+                frame += 1;
+                output.push((frame, Event::Release(Button::START)));
+                for c in to.to_string().chars() {
+                    output.push((frame, Event::Typed(Typed::Char(c))));
+                }
+                frame += 1;
+                output.push((frame, Event::Press(Button::START)));
+                held = &[Button::START];
+            },
+        }
+
+        frame += 1;
+    }
+
+    for &button in held {
+        output.push((frame, Event::Release(button)));
+    }
+
+    output
+}
+
+/// The given events, in the script format that `headless` reads.
+pub fn script(events: &[(FrameIndex, Event)]) -> String {
+    let mut output = String::new();
+    let mut typing: Option<(FrameIndex, String)> = None;
+
+    let name = |button| match button {
+        Button::A => "a",
+        Button::B => "b",
+        Button::SELECT => "select",
+        Button::START => "start",
+        Button::UP => "up",
+        Button::DOWN => "down",
+        Button::LEFT => "left",
+        _ => "right",
+    };
+
+    for &(frame, event) in events {
+        if let Event::Typed(Typed::Char(c)) = event {
+            match &mut typing {
+                Some((typing_frame, text)) if *typing_frame == frame => text.push(c),
+                _ => {
+                    if let Some((typing_frame, text)) = typing.take() {
+                        output.push_str(&format!("{typing_frame} type {text}\n"));
+                    }
+                    typing = Some((frame, c.to_string()));
+                },
+            }
+            continue
+        }
+
+        if let Some((typing_frame, text)) = typing.take() {
+            output.push_str(&format!("{typing_frame} type {text}\n"));
+        }
+
+        match event {
+            Event::Press(button) => output.push_str(&format!("{frame} press {}\n", name(button))),
+            Event::Release(button) => output.push_str(&format!("{frame} release {}\n", name(button))),
+            Event::Typed(Typed::Backspace) => output.push_str(&format!("{frame} backspace\n")),
+            Event::Typed(Typed::Char(_)) | Event::QuickSave | Event::QuickLoad => {},
+        }
+    }
+
+    if let Some((typing_frame, text)) = typing {
+        output.push_str(&format!("{typing_frame} type {text}\n"));
+    }
+
+    output
+}
+
+/// Makes up levels from a seed, so there is always another one to play. The
+/// same code always gives the same level, so levels can be shared by passing
+/// their codes around.
+pub mod generate {
+    use super::*;
+    use game::{xy, Budget, Channel, Terrain};
+    use xs::{Seed, Xs};
+
+    use core::ops::Range;
+
+    pub const MAX_LOOPS: u8 = 4;
+
+    /// Everything that decides which level gets generated. As text, this
+    /// looks like `2-1f3a`: the number of loops, then the seed in hex.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub struct Code {
+        /// How many times the level needs the player to go around, counting
+        /// the last time, which does not end in a jump.
+        pub loops: u8,
+        pub seed: Seed,
+    }
+
+    impl Code {
+        /// Parses a code, or just a number of loops, in which case the given
+        /// seed is used.
+        pub fn parse(text: &str, seed: Seed) -> Option<Code> {
+            let (loops, seed) = match text.split_once('-') {
+                Some((loops, hex)) => (loops, xs::seed_from_hex(hex)?),
+                None => (text, seed),
+            };
+
+            let loops = loops.parse().ok()
+                .filter(|loops| (1..=MAX_LOOPS).contains(loops))?;
+
+            Some(Code { loops, seed })
+        }
+    }
+
+    impl core::fmt::Display for Code {
+        fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+            let hex = self.seed.iter().map(|byte| format!("{byte:02x}")).collect::<String>();
+            let hex = hex.trim_start_matches('0');
+
+            write!(f, "{}-{}", self.loops, if hex.is_empty() { "0" } else { hex })
+        }
+    }
+
+    /// How many layouts to try before giving up on a code.
+    const ATTEMPTS: u32 = 32;
+
+    /// Time will not flow past this instant in generated levels. This keeps
+    /// the checks on each layout quick.
+    const LAST_INSTANT: InstantIndex = 39;
+
+    /// How many states the check on each layout can go through. Most
+    /// layouts need far fewer, and the rest are not worth the wait.
+    const MAX_STATES: usize = 500_000;
+
+    /// Generates the level for the given code. Each layout is checked with
+    /// `solve`, and only kept if it finds a way to win it in the given
+    /// number of loops.
+    /// `None` if none of the layouts tried worked out, which does not happen
+    /// for most codes.
+    ///
+    /// No layout can be won in fewer loops, whatever the player does, so
+    /// there is no need to check for that. The rooms are in a row, and the
+    /// only way from one to the next is through a door, which someone else
+    /// has to be holding open, from the plate in the room before. While a
+    /// loop is being played, the only others around are the selves from
+    /// earlier loops, and each loop starts where the one before it ended.
+    /// So each loop gets at most one room further than the earlier ones
+    /// did, and the first loop, with nobody to hold a door for it, stays in
+    /// the first room. That leaves the goal, in the last room, out of reach
+    /// until the last loop.
+    pub fn level(code: Code) -> Option<Level> {
+        let mut rng = xs::from_seed(code.seed);
+        let jumps = u32::from(code.loops.saturating_sub(1));
+
+        for _ in 0..ATTEMPTS {
+            let level = layout(&mut rng, code);
+            // Searching for a solution that is not there takes the longest,
+            // so this quickly rules out some of those first.
+            if !connected(&level) {
+                continue
+            }
+
+            let limits = Limits {
+                max_jumps: jumps,
+                max_instant: LAST_INSTANT,
+                max_states: MAX_STATES,
+            };
+            // Layouts that take the search too long are skipped along with
+            // those it cannot solve.
+            if solve(&level, Rules::default(), limits).is_err() {
+                continue
+            }
+
+            return Some(level)
+        }
+
+        None
+    }
+
+    /// Where the top left corner of each generated level goes, which is
+    /// where the levels that come with the game have it too.
+    const LEFT: u32 = 48;
+    const TOP: u32 = 28;
+
+    /// A row of rooms, one for each loop, with walls scattered about inside.
+    /// Each room but the last has a plate in it, which opens the door to
+    /// the next room. The player starts in the first room, and the goal is
+    /// in the last one.
+    fn layout(rng: &mut Xs, code: Code) -> Level {
+        let rooms = u32::from(code.loops.max(1));
+
+        let top = TOP;
+        let height = xs::range(rng, 3..6);
+        let bottom = top + height + 1;
+        let rows = top + 1..bottom;
+
+        let mut columns = Vec::with_capacity(rooms as usize);
+        let mut right = LEFT;
+        for _ in 0..rooms {
+            let width = xs::range(rng, 3..7);
+            columns.push(right + 1..right + 1 + width);
+            right += width + 1;
+        }
+
+        let mut terrain = Terrain::default();
+        let mut set = |(x, y): (u32, u32), cell| {
+            terrain.set(xy::x(x as xy::Inner), xy::y(y as xy::Inner), cell);
+        };
+
+        for x in LEFT..=right {
+            set((x, top), Cell::Wall);
+            set((x, bottom), Cell::Wall);
+        }
+        for y in rows.clone() {
+            set((LEFT, y), Cell::Wall);
+            for room in &columns {
+                set((room.end, y), Cell::Wall);
+            }
+        }
+
+        // Cells that need to stay clear of scattered walls.
+        let mut taken = Vec::new();
+
+        for (channel, room) in columns.iter().take(rooms as usize - 1).enumerate() {
+            let door = (room.end, xs::range(rng, rows.clone()));
+            set(door, Cell::Door(channel as Channel));
+            taken.push((door.0 - 1, door.1));
+            taken.push((door.0 + 1, door.1));
+        }
+
+        let start = pick(rng, &mut taken, columns[0].clone(), rows.clone());
+        for (channel, room) in columns.iter().take(rooms as usize - 1).enumerate() {
+            let plate = pick(rng, &mut taken, room.clone(), rows.clone());
+            set(plate, Cell::Plate(channel as Channel));
+        }
+        let goal = pick(rng, &mut taken, columns[rooms as usize - 1].clone(), rows.clone());
+        set(goal, Cell::Goal);
+
+        for room in &columns {
+            for y in rows.clone() {
+                for x in room.clone() {
+                    if !taken.contains(&(x, y)) && xs::range(rng, 0..5) == 0 {
+                        set((x, y), Cell::Wall);
+                    }
+                }
+            }
+        }
+
+        Level {
+            name: format!("generated {code}"),
+            terrain,
+            start: Player {
+                x: xy::x(start.0 as xy::Inner),
+                y: xy::y(start.1 as xy::Inner),
+            },
+            start_instant: 0,
+            budget: Budget {
+                last_instant: LAST_INSTANT,
+                ..<_>::default()
+            },
+        }
+    }
+
+    /// Whether every plate and goal can be walked to from the start, when
+    /// all the doors are open.
+    fn connected(level: &Level) -> bool {
+        let cell = |(x, y): (u32, u32)| {
+            level.terrain.get(xy::x(x as xy::Inner), xy::y(y as xy::Inner))
+        };
+
+        let start = (
+            u32::from(level.start.x.get().get()),
+            u32::from(level.start.y.get().get()),
+        );
+        let mut reached = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            // Levels are walled in, so there is no need to check the edges.
+            for next in [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)] {
+                if !cell(next).blocks() && reached.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+
+        level.terrain.iter()
+            .filter(|&(_, _, cell)| matches!(cell, Cell::Plate(_) | Cell::Goal))
+            .all(|(x, y, _)| reached.contains(&(u32::from(x.get().get()), u32::from(y.get().get()))))
+    }
+
+    /// A cell in the given area that is not taken yet, which is then taken.
+    fn pick(
+        rng: &mut Xs,
+        taken: &mut Vec<(u32, u32)>,
+        columns: Range<u32>,
+        rows: Range<u32>,
+    ) -> (u32, u32) {
+        // Rooms have more cells than there are things to put in each one,
+        // so this always ends.
+        loop {
+            let cell = (xs::range(rng, columns.clone()), xs::range(rng, rows.clone()));
+            if !taken.contains(&cell) {
+                taken.push(cell);
+                return cell
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod generating_a_level {
+        use super::*;
+
+        #[test]
+        fn gives_the_same_one_that_needs_the_loops_for_the_same_code() {
+            let code = Code {
+                loops: 2,
+                seed: [42; 16],
+            };
+
+            let generated = level(code).unwrap();
+
+            assert_eq!(level(code).as_ref(), Some(&generated));
+            assert_eq!(
+                solve(&generated, Rules::default(), Limits { max_jumps: 0, ..<_>::default() }),
+                Err(Unsolved::NoSolution)
+            );
+        }
+
+        #[test]
+        fn takes_the_code_it_writes_out() {
+            let code = Code {
+                loops: 3,
+                seed: xs::seed_from_hex("1f3a").unwrap(),
+            };
+
+            assert_eq!(code.to_string(), "3-1f3a");
+            assert_eq!(Code::parse("3-1f3a", <_>::default()), Some(code));
+            assert_eq!(Code::parse("3", code.seed), Some(code));
+            assert_eq!(Code::parse("0-1f3a", <_>::default()), None);
+            assert_eq!(Code::parse("5", <_>::default()), None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod solving_the_levels_that_come_with_the_game {
+    use super::*;
+
+    #[test]
+    fn gives_input_that_wins_them_when_played_back() {
+        for (index, level) in game::levels::all().iter().enumerate() {
+            let actions = solve(level, Rules::default(), Limits::default())
+                .unwrap_or_else(|why| panic!("could not solve {}: {why:?}", level.name));
+
+            let script = script(&events(&actions));
+            let mut state = app::State::new(<_>::default());
+            state.game_state.load_level(level);
+            let mut headless = headless::Headless::from_state(
+                state,
+                <_>::default(),
+                headless::parse_script(&script).unwrap(),
+            );
+            headless.run_to_end();
+
+            assert_eq!(
+                headless.state().game_state.time_mode,
+                TimeMode::Won,
+                "level {index} with this script:\n{script}"
+            );
+        }
+    }
+
+    #[test]
+    fn finds_nothing_when_the_limits_are_too_tight() {
+        let level = &game::levels::all()[1];
+
+        assert_eq!(
+            solve(level, Rules::default(), Limits { max_jumps: 0, ..<_>::default() }),
+            Err(Unsolved::NoSolution)
+        );
+    }
+}
+
+#[cfg(test)]
+mod solving_a_small_level {
+    use super::*;
+
+    /// The quickest way through is for one self to step onto the plate and
+    /// then right, and jump back, so that the next can step up through the
+    /// door and right onto the goal while the first is still on the plate.
+    /// Getting there takes five actions, and time flows up to instant 2.
+    const LEVEL: &str = "\
+[level]
+name = small
+start = 1, 1
+origin = 0, 0
+
+[objects]
+plate 0 = 1, 2
+door 0 = 3, 1
+goal = 4, 1
+
+[grid]
+######
+#....#
+#..#.#
+######
+";
+
+    #[test]
+    fn gives_a_shortest_solution() {
+        let level = game::level_file::parse(LEVEL).unwrap();
+
+        let actions = solve(&level, Rules::default(), Limits::default()).unwrap();
+
+        assert_eq!(actions.len(), 5, "{actions:?}");
+        assert_eq!(
+            actions.iter().filter(|action| matches!(action, Action::Jump { .. })).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn finds_nothing_when_every_way_within_the_limits_has_been_tried() {
+        let level = game::level_file::parse(LEVEL).unwrap();
+
+        for limits in [
+            Limits { max_jumps: 0, ..<_>::default() },
+            Limits { max_instant: 1, ..<_>::default() },
+        ] {
+            assert_eq!(
+                solve(&level, Rules::default(), limits),
+                Err(Unsolved::NoSolution),
+                "{limits:?}"
+            );
+        }
+    }
+}
@@ -12,8 +12,340 @@ mod wasm {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 fn run() {
     let params = platform::get_state_params();
     let state = app::State::new(params);
+
     platform::run(state);
-}
\ No newline at end of file
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run() {
+    let args = match args::parse(std::env::args().skip(1)) {
+        Ok(args::Parsed::Run(args)) => args,
+        Ok(args::Parsed::Help) => {
+            println!("{}", args::USAGE);
+            return
+        },
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{}", args::USAGE);
+            std::process::exit(2);
+        },
+    };
+
+    fn exit_with(e: impl std::fmt::Display) -> ! {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+
+    let options = platform::Options {
+        scale: args.scale,
+    };
+    let terminal = args.terminal;
+
+    fn run_state<S: platform_types::State + 'static>(
+        state: S,
+        options: platform::Options,
+        terminal: bool,
+    ) {
+        if terminal {
+            if let Err(e) = terminal::run(state) {
+                exit_with(format!("could not run in the terminal: {e}"));
+            }
+        } else {
+            platform::run_with_options(state, options);
+        }
+    }
+
+    fn load_walls(state: &mut app::State, path: Option<String>) {
+        if let Some(path) = path {
+            if let Err(e) = state.load_terrain(&path) {
+                exit_with(format!("could not load walls from {path}: {e}"));
+            }
+        }
+    }
+
+    fn load_level(state: &mut app::State, path: Option<String>) {
+        if let Some(path) = path {
+            if let Err(e) = state.load_level(&path) {
+                exit_with(format!("could not load level {path}: {e}"));
+            }
+        }
+    }
+
+    fn generate_level(state: &mut app::State, code: Option<String>, seed: xs::Seed) {
+        if let Some(code) = code {
+            // This was checked when parsing the arguments.
+            let code = solver::generate::Code::parse(&code, seed)
+                .unwrap_or_else(|| exit_with(format!("invalid code: {code}")));
+            println!("level code: {code}");
+
+            let level = solver::generate::level(code)
+                .unwrap_or_else(|| exit_with(format!("could not generate a level from {code}, try another code")));
+            state.game_state.load_level(&level);
+        }
+    }
+
+    let mut auto_shift = app::AutoShift::default();
+    if let Some(delay) = args.repeat_delay {
+        auto_shift.delay = delay;
+    }
+    if let Some(rate) = args.repeat_rate {
+        auto_shift.rate = rate;
+    }
+
+    let mut params = platform::get_state_params();
+    if let Some(seed) = args.seed {
+        params.0 = seed;
+    }
+
+    if let Some(path) = args.replay {
+        let replay = std::fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| replay::Replay::from_bytes(&bytes).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| exit_with(format!("could not load replay {path}: {e}")));
+
+        params.0 = replay.seed;
+        // This leaves the save path unset, so the quick saves and loads in
+        // the replay use the save it started with, rather than the file.
+        let state = app::State::from_start_bytes(params, &replay.start)
+            .unwrap_or_else(|e| exit_with(format!("could not load replay {path}: {e}")));
+
+        run_state(replay::Replayer::new(state, replay), options, terminal);
+        return
+    }
+
+    let seed = params.0;
+    let mut state = app::State::new(params);
+    state.save_path = Some(args::SAVE_PATH.into());
+    state.controls.auto_shift = auto_shift;
+
+    if let Some(path) = args.load {
+        if let Err(e) = state.load(&path) {
+            exit_with(format!("could not load {path}: {e}"));
+        }
+    }
+    load_walls(&mut state, args.walls);
+    load_level(&mut state, args.level);
+    generate_level(&mut state, args.generate, seed);
+
+    if args.skip_menu {
+        state.game_state.time_mode = game::TimeMode::Flowing;
+    }
+
+    if let Some(path) = args.record {
+        let start = state.start_bytes();
+        let recorder = std::fs::File::create(&path)
+            .and_then(|file| replay::Recorder::new(state, seed, &start, file))
+            .unwrap_or_else(|e| exit_with(format!("could not record to {path}: {e}")));
+
+        run_state(recorder, options, terminal);
+    } else {
+        run_state(state, options, terminal);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod args {
+    pub const SAVE_PATH: &str = "time-travel-sim.save";
+
+    pub const USAGE: &str = "\
+usage: time-travel-sim [OPTIONS]
+
+options:
+    --seed HEX        start with this seed, as logged on startup
+    --skip-menu       start playing right away
+    --scale N         make the window N times the base size of 480x320
+    --terminal        play in this terminal instead of a window
+    --load PATH       start from this save file
+    --walls PATH      put walls on the grid, as drawn in this text file
+    --level PATH      play the level in this level file
+    --generate CODE   play the level generated from this code, like 2-1f3a, or
+                      from just a number of loops, like 2, and the seed
+    --record PATH     record the session to this replay file
+    --replay PATH     play back this replay file
+    --repeat-delay N  start repeating a held button after N frames
+                      (defaults to 12)
+    --repeat-rate N   then repeat it every N frames (defaults to 3)
+    --help            print this message";
+
+    #[derive(Debug, Default, PartialEq, Eq)]
+    pub struct Args {
+        pub seed: Option<xs::Seed>,
+        pub skip_menu: bool,
+        pub scale: Option<u16>,
+        pub load: Option<String>,
+        pub walls: Option<String>,
+        pub level: Option<String>,
+        pub generate: Option<String>,
+        pub record: Option<String>,
+        pub replay: Option<String>,
+        pub terminal: bool,
+        pub repeat_delay: Option<u32>,
+        pub repeat_rate: Option<u32>,
+    }
+
+    #[derive(Debug, PartialEq, Eq)]
+    pub enum Parsed {
+        Run(Args),
+        Help,
+    }
+
+    pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Parsed, String> {
+        let mut output = Args::default();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+
+            match arg.as_str() {
+                "--seed" => output.seed = Some(parse_seed(&value()?)?),
+                "--skip-menu" => output.skip_menu = true,
+                "--scale" => {
+                    let scale = value()?;
+                    output.scale = match scale.parse() {
+                        Ok(0) | Err(_) => return Err(format!("invalid scale: {scale}")),
+                        Ok(scale) => Some(scale),
+                    };
+                },
+                "--load" => output.load = Some(value()?),
+                "--walls" => output.walls = Some(value()?),
+                "--level" => output.level = Some(value()?),
+                "--generate" => {
+                    let code = value()?;
+                    if solver::generate::Code::parse(&code, <_>::default()).is_none() {
+                        return Err(format!("invalid code: {code} (expected up to {} loops, then optionally - and up to 32 hex digits)", solver::generate::MAX_LOOPS));
+                    }
+                    output.generate = Some(code);
+                },
+                "--record" => output.record = Some(value()?),
+                "--replay" => output.replay = Some(value()?),
+                "--terminal" => output.terminal = true,
+                "--repeat-delay" => {
+                    let delay = value()?;
+                    output.repeat_delay = Some(
+                        delay.parse().map_err(|_| format!("invalid repeat delay: {delay}"))?
+                    );
+                },
+                "--repeat-rate" => {
+                    let rate = value()?;
+                    output.repeat_rate = match rate.parse() {
+                        Ok(0) | Err(_) => return Err(format!("invalid repeat rate: {rate}")),
+                        Ok(rate) => Some(rate),
+                    };
+                },
+                "--help" | "-h" => return Ok(Parsed::Help),
+                _ => return Err(format!("unknown argument: {arg}")),
+            }
+        }
+
+        if output.terminal && output.scale.is_some() {
+            return Err("--scale cannot be used with --terminal".to_string());
+        }
+
+        if output.load.is_some() && output.walls.is_some() {
+            return Err("--walls cannot be used with --load, since saves have their own walls".to_string());
+        }
+        if output.level.is_some() {
+            if output.load.is_some() {
+                return Err("--level cannot be used with --load".to_string());
+            }
+            if output.walls.is_some() {
+                return Err("--level cannot be used with --walls, since levels have their own walls".to_string());
+            }
+        }
+        if output.generate.is_some() {
+            if output.load.is_some() {
+                return Err("--generate cannot be used with --load".to_string());
+            }
+            if output.walls.is_some() {
+                return Err("--generate cannot be used with --walls, since levels have their own walls".to_string());
+            }
+            if output.level.is_some() {
+                return Err("--generate cannot be used with --level".to_string());
+            }
+        }
+
+        // A replay holds how the session started, so anything that changes
+        // that would only make it play out differently.
+        if output.replay.is_some() {
+            if output.seed.is_some() {
+                return Err("--seed cannot be used with --replay, which has its own seed".to_string());
+            }
+            for (given, name) in [
+                (output.skip_menu, "--skip-menu"),
+                (output.load.is_some(), "--load"),
+                (output.walls.is_some(), "--walls"),
+                (output.level.is_some(), "--level"),
+                (output.generate.is_some(), "--generate"),
+                (output.repeat_delay.is_some(), "--repeat-delay"),
+                (output.repeat_rate.is_some(), "--repeat-rate"),
+            ] {
+                if given {
+                    return Err(format!("{name} cannot be used with --replay, which starts the way the recording did"));
+                }
+            }
+            if output.record.is_some() {
+                return Err("--record cannot be used with --replay".to_string());
+            }
+        }
+
+        Ok(Parsed::Run(output))
+    }
+
+    fn parse_seed(hex: &str) -> Result<xs::Seed, String> {
+        xs::seed_from_hex(hex)
+            .ok_or_else(|| format!("invalid seed: {hex} (expected up to 32 hex digits)"))
+    }
+
+    #[cfg(test)]
+    mod parse_accepts {
+        use super::*;
+
+        fn parse_strs(args: &[&str]) -> Result<Parsed, String> {
+            parse(args.iter().map(|s| s.to_string()))
+        }
+
+        #[test]
+        fn all_the_options_that_go_together() {
+            let mut seed = xs::Seed::default();
+            seed[14] = 0x01;
+            seed[15] = 0x2a;
+
+            assert_eq!(
+                parse_strs(&["--seed", "12a", "--skip-menu", "--scale", "3", "--load", "a.save"]),
+                Ok(Parsed::Run(Args {
+                    seed: Some(seed),
+                    skip_menu: true,
+                    scale: Some(3),
+                    load: Some("a.save".to_string()),
+                    ..<_>::default()
+                }))
+            );
+        }
+
+        #[test]
+        fn nothing_that_changes_how_a_replay_starts() {
+            for args in [
+                &["--replay", "a.replay", "--seed", "12a"][..],
+                &["--replay", "a.replay", "--skip-menu"],
+                &["--load", "a.save", "--replay", "a.replay"],
+                &["--replay", "a.replay", "--walls", "walls.txt"],
+                &["--replay", "a.replay", "--level", "a.level"],
+                &["--generate", "2", "--replay", "a.replay"],
+                &["--replay", "a.replay", "--repeat-delay", "20"],
+                &["--repeat-rate", "5", "--replay", "a.replay"],
+            ] {
+                assert!(parse_strs(args).is_err(), "{args:?}");
+            }
+            assert!(parse_strs(&["--record", "a.replay", "--level", "a.level", "--repeat-rate", "5"]).is_ok());
+        }
+
+        #[test]
+        fn no_seeds_that_are_not_hex() {
+            assert!(parse_strs(&["--seed", "0x12"]).is_err());
+            assert!(parse_strs(&["--seed", &"f".repeat(33)]).is_err());
+        }
+    }
+}